@@ -0,0 +1,431 @@
+use serde::{Serialize, Deserialize};
+
+use crate::card::{AbilitiesFragment, Card, CardId, CardType};
+use crate::game::{GameState, Zone};
+use crate::rules::RulesError;
+use crate::targeting::TargetSpec;
+use crate::triggers::{AbilityEffect, StackItem};
+
+/// One part of an activated ability's cost. The mana is paid first, then the rest in this order.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AbilityCost
+{
+    /// {T}: the permanent taps, which a creature with summoning sickness can't do
+    Tap,
+    Mana(u32),
+    PayLife(i32),
+    Sacrifice,
+}
+
+/// "{cost}: effect". Abilities that add mana and don't target are mana abilities: they don't use
+/// the stack, and the payment solver activates them when a cost needs more mana than is floating.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ActivatedAbility
+{
+    pub costs: Vec<AbilityCost>,
+    pub effect: AbilityEffect,
+    #[serde(default)]
+    pub target: Option<TargetSpec>,
+}
+
+impl ActivatedAbility
+{
+    pub fn mana(costs: Vec<AbilityCost>, amount: u32) -> Self
+    {
+        ActivatedAbility { costs, effect: AbilityEffect::AddMana(amount), target: None }
+    }
+
+    pub fn is_mana_ability(&self) -> bool
+    {
+        matches!(self.effect, AbilityEffect::AddMana(_)) && self.target.is_none()
+    }
+}
+
+pub fn activated_abilities(card: &Card) -> &[ActivatedAbility]
+{
+    card.get::<AbilitiesFragment>()
+        .map(|af| af.abilities.as_slice())
+        .unwrap_or(&[])
+}
+
+pub fn add_activated_ability(card: &mut Card, ability: ActivatedAbility)
+{
+    card.builtin_or_default::<AbilitiesFragment>().abilities.push(ability);
+}
+
+fn mana_cost(costs: &[AbilityCost]) -> u32
+{
+    costs.iter().map(|c| if let AbilityCost::Mana(n) = c { *n } else { 0 }).sum()
+}
+
+fn life_cost(costs: &[AbilityCost]) -> i32
+{
+    costs.iter().map(|c| if let AbilityCost::PayLife(life) = c { *life } else { 0 }).sum()
+}
+
+/// A land or mana ability the payment solver may use. Lands make one mana implicitly.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ManaSource
+{
+    pub card: CardId,
+    /// Index into the card's activated abilities; None for a land's own mana
+    pub ability: Option<usize>,
+    pub amount: u32,
+}
+
+impl GameState
+{
+    fn can_tap(&self, card: &Card) -> bool
+    {
+        !crate::tappable::is_tapped(card)
+            && crate::tappable::is_tappable(card)
+            && !(self.has_effective_type(card, CardType::Creature) && crate::creature::has_summoning_sickness(card))
+    }
+
+    // Costs other than mana that `player` could pay for an ability of `card` right now
+    fn can_pay_other_costs(&self, player: usize, card: &Card, costs: &[AbilityCost]) -> bool
+    {
+        costs.iter().all(|cost| match *cost
+        {
+            AbilityCost::Tap => self.can_tap(card),
+            AbilityCost::PayLife(life) => self.players[player].life >= life,
+            AbilityCost::Mana(_) | AbilityCost::Sacrifice => true,
+        })
+    }
+
+    /// What `player` could tap or sacrifice for mana right now, cheapest first: lands, then mana
+    /// abilities that only tap, then ones that also cost life or the permanent itself. `exclude`
+    /// keeps a permanent that is already paying another part of the cost out of it.
+    pub fn mana_sources(&self, player: usize, exclude: Option<CardId>) -> Vec<ManaSource>
+    {
+        let mut sources: Vec<(usize, ManaSource)> = Vec::new();
+        for card in self.players[player].zones.get(&Zone::Battlefield).unwrap().iter().filter(|c| Some(c.id) != exclude)
+        {
+            if card.is_type(CardType::Land) && self.can_tap(card)
+            {
+                sources.push((0, ManaSource { card: card.id, ability: None, amount: 1 }));
+                continue;
+            }
+            // Only one use per permanent: it taps or is sacrificed. Mana costs would need paying for in turn.
+            let usable = activated_abilities(card).iter().enumerate().find(|(_, ability)| ability.is_mana_ability()
+                && ability.costs.iter().any(|c| matches!(c, AbilityCost::Tap | AbilityCost::Sacrifice))
+                && !ability.costs.iter().any(|c| matches!(c, AbilityCost::Mana(_)))
+                && self.can_pay_other_costs(player, card, &ability.costs));
+            if let Some((index, ability)) = usable
+                && let AbilityEffect::AddMana(amount) = ability.effect
+            {
+                let rank = if ability.costs.iter().all(|c| *c == AbilityCost::Tap) { 1 } else { 2 };
+                sources.push((rank, ManaSource { card: card.id, ability: Some(index), amount }));
+            }
+        }
+        sources.sort_by_key(|(rank, _)| *rank);
+        sources.into_iter().map(|(_, source)| source).collect()
+    }
+
+    /// Mana in `player`'s pool plus what their sources could still make.
+    pub fn mana_available_to(&self, player: usize, exclude: Option<CardId>) -> u32
+    {
+        self.players[player].mana_pool + self.mana_sources(player, exclude).iter().map(|s| s.amount).sum::<u32>()
+    }
+
+    pub fn add_mana(&mut self, player: usize, amount: u32)
+    {
+        self.players[player].mana_pool += amount;
+    }
+
+    /// Unspent mana is lost between steps.
+    pub fn empty_mana_pools(&mut self)
+    {
+        for player in self.players.iter_mut()
+        {
+            player.mana_pool = 0;
+        }
+    }
+
+    // The sources pay_mana would activate for `amount` on top of the floating mana, with their costs
+    fn mana_plan(&self, player: usize, amount: u32, exclude: Option<CardId>) -> Result<Vec<(ManaSource, Vec<AbilityCost>)>, RulesError>
+    {
+        let available = self.mana_available_to(player, exclude);
+        if amount > available
+        {
+            return Err(RulesError::InsufficientMana { needed: amount, available });
+        }
+
+        let mut floating = self.players[player].mana_pool;
+        let mut plan = Vec::new();
+        for source in self.mana_sources(player, exclude)
+        {
+            if floating >= amount
+            {
+                break;
+            }
+            let costs = match source.ability
+            {
+                Some(index) => activated_abilities(self.find_permanent(source.card).unwrap().1)[index].costs.clone(),
+                None => vec![AbilityCost::Tap],
+            };
+            floating += source.amount;
+            plan.push((source, costs));
+        }
+        Ok(plan)
+    }
+
+    // Each source only checks its own life cost, so make sure `player` can pay them all together
+    fn check_life(&self, player: usize, needed: i32) -> Result<(), RulesError>
+    {
+        let life = self.players[player].life;
+        if needed > life
+        {
+            return Err(RulesError::NotEnoughLife { needed, life });
+        }
+        Ok(())
+    }
+
+    /// Spend `amount` mana from `player`'s pool, first activating sources into it until there is
+    /// enough. Whatever a source makes beyond the cost stays in the pool. Nothing is paid unless
+    /// all of it can be.
+    pub fn pay_mana(&mut self, player: usize, amount: u32, exclude: Option<CardId>) -> Result<(), RulesError>
+    {
+        let plan = self.mana_plan(player, amount, exclude)?;
+        self.check_life(player, plan.iter().map(|(_, costs)| life_cost(costs)).sum())?;
+
+        for (source, costs) in plan
+        {
+            self.pay_costs(player, source.card, &costs)?;
+            self.add_mana(player, source.amount);
+        }
+        self.players[player].mana_pool -= amount;
+        Ok(())
+    }
+
+    // Pay an ability's costs for the permanent `source` that `player` controls. The mana goes
+    // first, since it is the part that can fail, and `source` never helps pay it.
+    fn pay_costs(&mut self, player: usize, source: CardId, costs: &[AbilityCost]) -> Result<(), RulesError>
+    {
+        let mana = mana_cost(costs);
+        if mana > 0
+        {
+            self.pay_mana(player, mana, Some(source))?;
+        }
+        for cost in costs
+        {
+            match *cost
+            {
+                AbilityCost::Tap =>
+                {
+                    let battlefield = self.players[player].zones.get_mut(&Zone::Battlefield).unwrap();
+                    if let Some(card) = battlefield.iter_mut().find(|c| c.id == source)
+                    {
+                        crate::tappable::set_tapped(card, true);
+                    }
+                }
+                AbilityCost::Mana(_) => {}
+                AbilityCost::PayLife(life) => self.lose_life(player, life),
+                // Nothing can stop a permanent being destroyed yet, so sacrificing it is the same
+                AbilityCost::Sacrifice => self.destroy_permanent(source),
+            }
+        }
+        Ok(())
+    }
+
+    /// Activate ability `ability` of the permanent at `index` on the current player's battlefield.
+    /// Mana abilities can be activated at any time, others only in the controller's main phases.
+    /// Its costs are paid, then a mana ability adds its mana at once; anything else goes on the
+    /// stack, choosing targets on the way.
+    pub fn activate_ability(&mut self, index: usize, ability: usize) -> Result<(), RulesError>
+    {
+        self.expect_no_pending_choice()?;
+        let player = self.current_player_index;
+        let card = self.permanent(player, index)?;
+        let activated = activated_abilities(card).get(ability).ok_or(RulesError::NoSuchAbility(ability))?.clone();
+        let is_mana_ability = activated.is_mana_ability();
+        if !is_mana_ability
+        {
+            self.expect_main_phase()?;
+        }
+
+        let mana = mana_cost(&activated.costs);
+        let taps = activated.costs.contains(&AbilityCost::Tap);
+        if taps && crate::tappable::is_tapped(card)
+        {
+            return Err(RulesError::Tapped(index));
+        }
+        if taps && !self.can_tap(card)
+        {
+            return Err(RulesError::SummoningSick(index));
+        }
+        // The ability's own life cost and whatever the sources paying its mana cost in life
+        let plan = self.mana_plan(player, mana, Some(card.id))?;
+        self.check_life(player, life_cost(&activated.costs) + plan.iter().map(|(_, costs)| life_cost(costs)).sum::<i32>())?;
+
+        let item = StackItem
+        {
+            controller: player,
+            source: card.id,
+            source_name: card.name.clone(),
+            effect: activated.effect,
+            source_types: self.effective_types(card),
+            target_spec: activated.target,
+            targets: None,
+        };
+        self.pay_costs(player, item.source, &activated.costs)?;
+        if is_mana_ability
+            && let AbilityEffect::AddMana(amount) = item.effect
+        {
+            self.add_mana(player, amount);
+            return Ok(());
+        }
+        self.pending_triggers.push(item);
+        self.resolve_triggers();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::card::{Deck, forest, grizzly_bears, llanowar_elves};
+    use crate::game::GameStep;
+
+    #[test]
+    fn mana_dorks_help_pay_once_they_can_tap()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        let mut elves = llanowar_elves();
+        crate::creature::set_summoning_sickness(&mut elves, true);
+        gs.players[0].zones.insert(Zone::Battlefield, vec![forest(), elves]);
+        gs.players[0].zones.insert(Zone::Hand, vec![grizzly_bears()]);
+        gs.step = GameStep::Main;
+
+        // Summoning sick elves can't tap for mana
+        assert_eq!(gs.available_mana(), 1);
+        assert!(matches!(gs.cast_creature(0), Err(RulesError::InsufficientMana { needed: 2, available: 1 })));
+
+        crate::creature::set_summoning_sickness(&mut gs.players[0].zones.get_mut(&Zone::Battlefield).unwrap()[1], false);
+        assert_eq!(gs.available_mana(), 2);
+        gs.cast_creature(0).unwrap();
+        let battlefield = &gs.players[0].zones[&Zone::Battlefield];
+        assert!(crate::tappable::is_tapped(&battlefield[0]) && crate::tappable::is_tapped(&battlefield[1]));
+        assert_eq!(gs.available_mana(), 0);
+    }
+
+    #[test]
+    fn activated_abilities_pay_costs_and_use_the_stack()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        gs.step = GameStep::Main;
+        let mut altar = forest();
+        altar.remove_type(CardType::Land);
+        add_activated_ability(&mut altar, ActivatedAbility
+        {
+            costs: vec![AbilityCost::Mana(1), AbilityCost::PayLife(2), AbilityCost::Sacrifice],
+            effect: AbilityEffect::DrawCards(2),
+            target: None,
+        });
+        gs.players[0].zones.insert(Zone::Library, vec![forest(), forest()]);
+        gs.players[0].zones.insert(Zone::Hand, Vec::new());
+        gs.players[0].zones.insert(Zone::Battlefield, vec![altar, llanowar_elves()]);
+
+        assert_eq!(gs.activate_ability(0, 1), Err(RulesError::NoSuchAbility(1)));
+        gs.step = GameStep::DeclareAttackers;
+        assert_eq!(gs.activate_ability(0, 0), Err(RulesError::WrongStep { expected: GameStep::Main, actual: GameStep::DeclareAttackers }));
+        gs.step = GameStep::SecondMain;
+
+        gs.activate_ability(0, 0).unwrap();
+        assert_eq!(gs.players[0].life, 18);
+        assert_eq!(gs.players[0].zones[&Zone::Hand].len(), 2);
+        assert_eq!(gs.players[0].zones[&Zone::Graveyard].len(), 1);
+        assert!(crate::tappable::is_tapped(&gs.players[0].zones[&Zone::Battlefield][0]));
+    }
+
+    #[test]
+    fn mana_pool_carries_leftovers_until_the_step_ends()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        let mut cradle = forest();
+        add_activated_ability(&mut cradle, ActivatedAbility::mana(vec![AbilityCost::Tap], 3));
+        cradle.remove_type(CardType::Land);
+        gs.players[0].zones.insert(Zone::Battlefield, vec![cradle]);
+        gs.players[0].zones.insert(Zone::Hand, vec![grizzly_bears(), llanowar_elves()]);
+        gs.players[0].control = crate::strategy::PlayerControl::Human;
+        gs.step = GameStep::Main;
+
+        // Three mana from one tap pays for both spells
+        gs.cast_creature(0).unwrap();
+        assert_eq!(gs.players[0].mana_pool, 1);
+        gs.cast_creature(0).unwrap();
+        assert_eq!(gs.players[0].mana_pool, 0);
+
+        // Mana abilities can be activated by hand, and what isn't spent is gone after the step
+        crate::creature::set_summoning_sickness(&mut gs.players[0].zones.get_mut(&Zone::Battlefield).unwrap()[2], false);
+        gs.activate_ability(2, 0).unwrap();
+        assert_eq!(gs.players[0].mana_pool, 1);
+        gs.pass().unwrap();
+        assert_eq!(gs.players[0].mana_pool, 0);
+    }
+
+    #[test]
+    fn life_costs_are_totalled_before_anything_is_paid()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        let shrine = ||
+        {
+            let mut shrine = forest();
+            shrine.remove_type(CardType::Land);
+            add_activated_ability(&mut shrine, ActivatedAbility::mana(vec![AbilityCost::Tap, AbilityCost::PayLife(2)], 1));
+            shrine
+        };
+        gs.players[0].zones.insert(Zone::Battlefield, vec![shrine(), shrine()]);
+        gs.players[0].zones.insert(Zone::Hand, vec![grizzly_bears()]);
+        gs.players[0].life = 3;
+        gs.step = GameStep::Main;
+
+        // Either shrine alone is affordable, both together are not
+        assert_eq!(gs.available_mana(), 2);
+        assert_eq!(gs.cast_creature(0), Err(RulesError::NotEnoughLife { needed: 4, life: 3 }));
+        assert_eq!(gs.players[0].zones[&Zone::Hand].len(), 1);
+        assert_eq!(gs.players[0].life, 3);
+        assert!(gs.players[0].zones[&Zone::Battlefield].iter().all(|card| !crate::tappable::is_tapped(card)));
+
+        // Mana abilities still work outside the main phases
+        gs.step = GameStep::EndStep;
+        gs.activate_ability(0, 0).unwrap();
+        assert_eq!((gs.players[0].mana_pool, gs.players[0].life), (1, 1));
+    }
+
+    #[test]
+    fn an_ability_cant_help_pay_its_own_mana_cost()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        gs.step = GameStep::Main;
+        let mut grove = forest();
+        add_activated_ability(&mut grove, ActivatedAbility
+        {
+            costs: vec![AbilityCost::PayLife(2), AbilityCost::Mana(1)],
+            effect: AbilityEffect::GainLife(5),
+            target: None,
+        });
+        add_activated_ability(&mut grove, ActivatedAbility
+        {
+            costs: vec![AbilityCost::Sacrifice, AbilityCost::Mana(1)],
+            effect: AbilityEffect::DrawCards(1),
+            target: None,
+        });
+        gs.players[0].zones.insert(Zone::Battlefield, vec![grove]);
+
+        // The land is the only mana around, and it can't pay for its own abilities
+        let unpaid = Err(RulesError::InsufficientMana { needed: 1, available: 0 });
+        assert_eq!(gs.activate_ability(0, 0), unpaid);
+        assert_eq!(gs.activate_ability(0, 1), unpaid);
+        assert_eq!(gs.players[0].life, 20);
+        assert_eq!(gs.players[0].zones[&Zone::Battlefield].len(), 1);
+        assert!(!crate::tappable::is_tapped(&gs.players[0].zones[&Zone::Battlefield][0]));
+
+        // With another land it works, and the mana is paid before the sacrifice
+        gs.players[0].zones.get_mut(&Zone::Battlefield).unwrap().push(forest());
+        gs.activate_ability(0, 1).unwrap();
+        assert_eq!(gs.players[0].zones[&Zone::Battlefield].len(), 1);
+        assert!(crate::tappable::is_tapped(&gs.players[0].zones[&Zone::Battlefield][0]));
+    }
+}
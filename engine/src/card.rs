@@ -1,7 +1,20 @@
-use std::collections::HashMap;
-use std::any::Any;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use serde::{Serialize, Deserialize};
 
+use crate::fragments::{BuiltinFragment, FragmentError, FragmentType, Fragments};
+
+/// Identifies one card object for as long as it exists, e.g. so effects can follow it.
+pub type CardId = u64;
+
+static NEXT_CARD_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A fresh id for a new card object.
+pub fn next_card_id() -> CardId
+{
+    NEXT_CARD_ID.fetch_add(1, Ordering::Relaxed)
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CardType 
 {
@@ -9,26 +22,97 @@ pub enum CardType
     Creature,
 }
 
+impl CardType
+{
+    pub const ALL: [CardType; 2] = [CardType::Land, CardType::Creature];
+
+    /// Names of the fragments every card of this type carries.
+    pub fn required_fragments(self) -> &'static [&'static str]
+    {
+        match self
+        {
+            CardType::Land => &[TappableFragment::NAME],
+            CardType::Creature => &[CreatureFragment::NAME, TappableFragment::NAME],
+        }
+    }
+
+    /// The type a fragment belongs to, if only cards of one type may have it.
+    pub fn owning(fragment: &str) -> Option<CardType>
+    {
+        let mut owners = CardType::ALL.into_iter().filter(|t| t.required_fragments().contains(&fragment));
+        match (owners.next(), owners.next())
+        {
+            (Some(t), None) => Some(t),
+            _ => None,
+        }
+    }
+}
+
 // Use composition so only creatures have power/toughness.
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CreatureStats
 {
     pub power: u8,
     pub toughness: u8,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
-pub enum CardFragmentKind
+/// A kind of counter that can sit on a card. Serialized as its printed name, e.g. "+1/+1".
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum CounterKind
 {
-    Creature,
-    Tappable,
+    PlusOne,
+    MinusOne,
+    Loyalty,
+    Named(String),
 }
 
-pub trait Fragment: Any + Send + Sync
+impl From<String> for CounterKind
 {
-    fn as_any(&self) -> &dyn Any;
-    fn as_any_mut(&mut self) -> &mut dyn Any;
-    fn box_clone(&self) -> Box<dyn Fragment>;
+    fn from(name: String) -> Self
+    {
+        match name.as_str()
+        {
+            "+1/+1" => CounterKind::PlusOne,
+            "-1/-1" => CounterKind::MinusOne,
+            "loyalty" => CounterKind::Loyalty,
+            _ => CounterKind::Named(name),
+        }
+    }
+}
+
+impl From<CounterKind> for String
+{
+    fn from(kind: CounterKind) -> Self
+    {
+        kind.to_string()
+    }
+}
+
+impl std::fmt::Display for CounterKind
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            CounterKind::PlusOne => write!(f, "+1/+1"),
+            CounterKind::MinusOne => write!(f, "-1/-1"),
+            CounterKind::Loyalty => write!(f, "loyalty"),
+            CounterKind::Named(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Keyword abilities the engine knows about.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Keyword
+{
+    /// Can't be the target of abilities opponents control
+    Hexproof,
+    /// Can't be the target of any ability
+    Shroud,
+    /// Can't be targeted by abilities whose source has this card type
+    ProtectionFrom(CardType),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -38,131 +122,117 @@ pub struct CreatureFragment
     pub summoning_sickness: bool,
 }
 
-impl Fragment for CreatureFragment
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TappableFragment
 {
-    fn as_any(&self) -> &dyn Any
-    {
-        self
-    }
+    pub tapped: bool,
+}
 
-    fn as_any_mut(&mut self) -> &mut dyn Any
-    {
-        self
-    }
+// How many of each kind of counter a card has; kinds with none are left out
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CountersFragment
+{
+    pub counters: BTreeMap<CounterKind, u32>,
+}
 
-    fn box_clone(&self) -> Box<dyn Fragment>
-    {
-        Box::new(CreatureFragment { stats: self.stats, summoning_sickness: self.summoning_sickness })
-    }
+// The card's triggered abilities, in the order they are printed
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TriggersFragment
+{
+    pub abilities: Vec<crate::triggers::TriggeredAbility>,
 }
 
-impl Fragment for TappableFragment
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct KeywordsFragment
 {
-    fn as_any(&self) -> &dyn Any
-    {
-        self
-    }
+    pub keywords: Vec<Keyword>,
+}
 
-    fn as_any_mut(&mut self) -> &mut dyn Any
-    {
-        self
-    }
+// The card's activated abilities, in the order they are printed
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AbilitiesFragment
+{
+    pub abilities: Vec<crate::abilities::ActivatedAbility>,
+}
 
-    fn box_clone(&self) -> Box<dyn Fragment>
-    {
-        Box::new(TappableFragment { tapped: self.tapped })
-    }
+impl FragmentType for CreatureFragment
+{
+    const NAME: &'static str = "Creature";
 }
 
-impl Clone for Box<dyn Fragment>
+impl FragmentType for TappableFragment
 {
-    fn clone(&self) -> Box<dyn Fragment>
-    {
-        self.box_clone()
-    }
+    const NAME: &'static str = "Tappable";
 }
 
-// Serializable representation of fragments
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub enum SerializableFragment
+impl FragmentType for CountersFragment
 {
-    Creature(CreatureFragment),
-    Tappable(TappableFragment),
+    const NAME: &'static str = "Counters";
 }
 
-impl SerializableFragment
+impl FragmentType for TriggersFragment
 {
-    /// Convert to trait object
-    pub fn to_fragment(&self) -> Box<dyn Fragment>
-    {
-        match self
-        {
-            SerializableFragment::Creature(cf) => Box::new(cf.clone()),
-            SerializableFragment::Tappable(tf) => Box::new(tf.clone()),
-        }
-    }
+    const NAME: &'static str = "Triggers";
+}
 
-    /// Convert from trait object (best effort)
-    pub fn from_fragment(fragment: &dyn Fragment) -> Option<Self>
-    {
-        if let Some(cf) = fragment.as_any().downcast_ref::<CreatureFragment>()
-        {
-            return Some(SerializableFragment::Creature(cf.clone()));
-        }
-        if let Some(tf) = fragment.as_any().downcast_ref::<TappableFragment>()
-        {
-            return Some(SerializableFragment::Tappable(tf.clone()));
-        }
-        None
-    }
+impl FragmentType for KeywordsFragment
+{
+    const NAME: &'static str = "Keywords";
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct TappableFragment
+impl FragmentType for AbilitiesFragment
 {
-    pub tapped: bool,
+    const NAME: &'static str = "Abilities";
 }
 
+/// A card object. Its types and fragments only change together (add_type, remove_type, insert,
+/// remove), and saved cards are checked as they load, so they always pass `validate`.
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "SavedCard")]
 pub struct Card
 {
+    pub id: CardId,
     pub name: String,
-    pub card_types: Vec<CardType>,
+    card_types: Vec<CardType>,
     pub cost: u32,
-    #[serde(serialize_with = "serialize_fragments", deserialize_with = "deserialize_fragments")]
-    pub fragments: HashMap<CardFragmentKind, Box<dyn Fragment>>,
-}
-
-// Custom serialization for fragments
-fn serialize_fragments<S>(
-    fragments: &HashMap<CardFragmentKind, Box<dyn Fragment>>,
-    serializer: S,
-) -> Result<S::Ok, S::Error>
-where
-    S: serde::Serializer,
-{
-    let serializable: HashMap<CardFragmentKind, SerializableFragment> = fragments
-        .iter()
-        .filter_map(|(k, v)| {
-            SerializableFragment::from_fragment(v.as_ref()).map(|sf| (*k, sf))
-        })
-        .collect();
-    serializable.serialize(serializer)
-}
-
-// Custom deserialization for fragments
-fn deserialize_fragments<'de, D>(
-    deserializer: D,
-) -> Result<HashMap<CardFragmentKind, Box<dyn Fragment>>, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    let serializable: HashMap<CardFragmentKind, SerializableFragment> =
-        HashMap::deserialize(deserializer)?;
-    Ok(serializable
-        .into_iter()
-        .map(|(k, v)| (k, v.to_fragment()))
-        .collect())
+    fragments: Fragments,
+    // Tokens cease to exist when they leave the battlefield
+    pub is_token: bool,
+}
+
+// A card as saved, before its types and fragments are checked
+#[derive(Deserialize)]
+struct SavedCard
+{
+    #[serde(default)]
+    id: CardId,
+    name: String,
+    card_types: Vec<CardType>,
+    cost: u32,
+    #[serde(default)]
+    fragments: Fragments,
+    #[serde(default)]
+    is_token: bool,
+}
+
+impl TryFrom<SavedCard> for Card
+{
+    type Error = CardError;
+
+    fn try_from(saved: SavedCard) -> Result<Self, Self::Error>
+    {
+        let card = Card
+        {
+            id: saved.id,
+            name: saved.name,
+            card_types: saved.card_types,
+            cost: saved.cost,
+            fragments: saved.fragments,
+            is_token: saved.is_token,
+        };
+        card.validate()?;
+        Ok(card)
+    }
 }
 
 impl std::fmt::Debug for Card
@@ -170,34 +240,170 @@ impl std::fmt::Debug for Card
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
     {
         f.debug_struct("Card")
+            .field("id", &self.id)
             .field("name", &self.name)
             .field("card_types", &self.card_types)
             .field("cost", &self.cost)
+            .field("is_token", &self.is_token)
             .finish()
     }
 }
 
 impl Card
 {
+    /// A new card object with no types or fragments yet.
+    pub fn new(name: &str, cost: u32) -> Card
+    {
+        Card { id: next_card_id(), name: String::from(name), card_types: Vec::new(), cost, fragments: Fragments::default(), is_token: false }
+    }
+
+    /// The card's printed types.
+    pub fn card_types(&self) -> &[CardType]
+    {
+        &self.card_types
+    }
+
+    pub fn fragments(&self) -> &Fragments
+    {
+        &self.fragments
+    }
+
+    /// The card's fragment of type `T`, if it has one.
+    pub fn get<T: FragmentType>(&self) -> Option<&T>
+    {
+        self.fragments.get()
+    }
+
+    pub fn get_mut<T: FragmentType>(&mut self) -> Option<&mut T>
+    {
+        self.fragments.get_mut()
+    }
+
+    /// Add or replace the card's `T`. A fragment only cards of one type may have needs the card to
+    /// have that type first.
+    pub fn insert<T: FragmentType>(&mut self, fragment: T) -> Result<(), CardError>
+    {
+        if let Some(card_type) = CardType::owning(T::NAME).filter(|t| !self.is_type(*t))
+        {
+            return Err(CardError::FragmentWithoutType { card: self.name.clone(), fragment: T::NAME, card_type });
+        }
+        self.fragments.insert(fragment).map_err(CardError::Fragment)
+    }
+
+    /// Take the card's `T` off it. Fragments one of its types requires stay; remove_type takes those.
+    pub fn remove<T: FragmentType>(&mut self) -> Option<T>
+    {
+        if self.card_types.iter().any(|t| t.required_fragments().contains(&T::NAME))
+        {
+            return None;
+        }
+        self.fragments.remove()
+    }
+
+    // Copy in `other`'s fragments, giving the card any type one of them belongs to
+    pub(crate) fn merge_fragments(&mut self, other: &Fragments)
+    {
+        self.fragments.merge(other);
+        for card_type in other.names().filter_map(CardType::owning)
+        {
+            self.add_type(card_type);
+        }
+    }
+
+    // Add or replace one of the engine's own fragments
+    pub(crate) fn set_builtin<T: BuiltinFragment>(&mut self, fragment: T)
+    {
+        self.fragments.insert_builtin(fragment);
+    }
+
+    // The card's built-in `T`, added with its default values if it has none
+    pub(crate) fn builtin_or_default<T: BuiltinFragment + Default>(&mut self) -> &mut T
+    {
+        if !self.fragments.contains::<T>()
+        {
+            self.fragments.insert_builtin(T::default());
+        }
+        self.fragments.get_mut().unwrap()
+    }
+
     pub fn is_type(&self, t: CardType) -> bool
     {
         self.card_types.iter().any(|ct| *ct == t)
     }
 
+    /// Give the card a type along with the fragments the type requires; a new creature starts as a 0/0.
     pub fn add_type(&mut self, t: CardType)
     {
         if !self.card_types.contains(&t)
         {
             self.card_types.push(t);
         }
+        for &fragment in t.required_fragments()
+        {
+            if !self.fragments.names().any(|n| n == fragment)
+            {
+                self.add_required_fragment(fragment);
+            }
+        }
     }
 
+    // A fragment a type requires, as a card that just gained the type first holds it
+    fn add_required_fragment(&mut self, name: &str)
+    {
+        match name
+        {
+            TappableFragment::NAME => self.set_builtin(TappableFragment { tapped: false }),
+            CreatureFragment::NAME => self.set_builtin(CreatureFragment { stats: CreatureStats { power: 0, toughness: 0 }, summoning_sickness: false }),
+            _ => unreachable!("no starting value for the {} fragment", name),
+        }
+    }
+
+    /// Take a type away, along with the fragments only that type has.
     pub fn remove_type(&mut self, t: CardType)
     {
         if let Some(pos) = self.card_types.iter().position(|ct| *ct == t)
         {
             self.card_types.remove(pos);
         }
+        let owned: Vec<&'static str> = self.fragments.names().filter(|n| CardType::owning(n) == Some(t)).collect();
+        for name in owned
+        {
+            self.fragments.remove_named(name);
+        }
+    }
+
+    /// Check the card's types and fragments agree: each type has the fragments it requires and
+    /// no fragment belongs to a type the card lacks.
+    pub fn validate(&self) -> Result<(), CardError>
+    {
+        for &card_type in &self.card_types
+        {
+            if let Some(&fragment) = card_type.required_fragments().iter().find(|name| !self.fragments.names().any(|n| n == **name))
+            {
+                return Err(CardError::MissingFragment { card: self.name.clone(), card_type, fragment });
+            }
+        }
+        for fragment in self.fragments.names()
+        {
+            if let Some(card_type) = CardType::owning(fragment).filter(|t| !self.is_type(*t))
+            {
+                return Err(CardError::FragmentWithoutType { card: self.name.clone(), fragment, card_type });
+            }
+        }
+        Ok(())
+    }
+
+    /// The card's copiable values (name, types, cost and fragments) without its status: the copy
+    /// is a new object that is untapped, free of summoning sickness and counters, and not a token.
+    pub fn copiable(&self) -> Card
+    {
+        let mut copy = self.clone();
+        copy.id = next_card_id();
+        copy.is_token = false;
+        copy.fragments.remove::<CountersFragment>();
+        crate::tappable::set_tapped(&mut copy, false);
+        crate::creature::set_summoning_sickness(&mut copy, false);
+        copy
     }
 }
 
@@ -227,46 +433,128 @@ impl Deck
 
         Deck { cards }
     }
+
+    /// Build a deck from card name -> number of copies.
+    pub fn from_counts(counts: &BTreeMap<String, u32>) -> Result<Deck, UnknownCard>
+    {
+        let mut cards = Vec::new();
+        for (name, &copies) in counts
+        {
+            let card = card_by_name(name).ok_or_else(|| UnknownCard(name.clone()))?;
+            for _ in 0..copies
+            {
+                cards.push(card.clone());
+            }
+        }
+
+        Ok(Deck { cards })
+    }
+
+    /// Number of copies of each card, by name.
+    pub fn counts(&self) -> BTreeMap<String, u32>
+    {
+        let mut counts = BTreeMap::new();
+        for card in &self.cards
+        {
+            *counts.entry(card.name.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+/// A decklist named a card this engine doesn't know how to build.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownCard(pub String);
+
+impl std::fmt::Display for UnknownCard
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "unknown card: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownCard {}
+
+/// A card whose types and fragments disagree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CardError
+{
+    /// It has a type but not a fragment every card of that type has
+    MissingFragment { card: String, card_type: CardType, fragment: &'static str },
+    /// It has a fragment only cards of another type may have
+    FragmentWithoutType { card: String, fragment: &'static str, card_type: CardType },
+    /// The fragment's name belongs to another fragment type
+    Fragment(FragmentError),
+}
+
+impl std::fmt::Display for CardError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            CardError::MissingFragment { card, card_type, fragment } => write!(f, "{} is a {:?} without a {} fragment", card, card_type, fragment),
+            CardError::FragmentWithoutType { card, fragment, card_type } => write!(f, "{} has a {} fragment but is not a {:?}", card, fragment, card_type),
+            CardError::Fragment(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for CardError {}
+
+/// Every card the engine can build, by name.
+pub fn card_by_name(name: &str) -> Option<Card>
+{
+    match name
+    {
+        "Forest" => Some(forest()),
+        "Grizzly Bears" => Some(grizzly_bears()),
+        "Llanowar Elves" => Some(llanowar_elves()),
+        _ => None,
+    }
 }
 
 pub fn forest() -> Card 
 {
-    Card
+    let mut card = Card
     {
+        id: next_card_id(),
         name: String::from("Forest"),
         card_types: vec![CardType::Land],
         cost: 0,
-        fragments: {
-            let mut m = HashMap::new();
-            m.insert(
-                CardFragmentKind::Tappable,
-                Box::new(TappableFragment { tapped: false }) as Box<dyn Fragment>,
-            );
-            m
-        },
-    }
+        fragments: Fragments::default(),
+        is_token: false,
+    };
+    card.set_builtin(TappableFragment { tapped: false });
+    card
 }
 
 pub fn grizzly_bears() -> Card 
 {
-    Card
+    let mut card = Card
     {
+        id: next_card_id(),
         name: String::from("Grizzly Bears"),
         card_types: vec![CardType::Creature],
         cost: 2,
-        fragments: {
-            let mut m = HashMap::new();
-            m.insert(
-                CardFragmentKind::Creature,
-                Box::new(CreatureFragment { stats: CreatureStats { power: 2, toughness: 2 }, summoning_sickness: false }) as Box<dyn Fragment>,
-            );
-            m.insert(
-                CardFragmentKind::Tappable,
-                Box::new(TappableFragment { tapped: false }) as Box<dyn Fragment>,
-            );
-            m
-        },
-    }
+        fragments: Fragments::default(),
+        is_token: false,
+    };
+    card.set_builtin(CreatureFragment { stats: CreatureStats { power: 2, toughness: 2 }, summoning_sickness: false });
+    card.set_builtin(TappableFragment { tapped: false });
+    card
+}
+
+/// A 1/1 for one with "{T}: Add one mana".
+pub fn llanowar_elves() -> Card
+{
+    let mut card = grizzly_bears();
+    card.name = String::from("Llanowar Elves");
+    card.cost = 1;
+    crate::creature::add_creature_fragment(&mut card, 1, 1);
+    crate::abilities::add_activated_ability(&mut card, crate::abilities::ActivatedAbility::mana(vec![crate::abilities::AbilityCost::Tap], 1));
+    card
 }
 
 #[cfg(test)]
@@ -287,19 +575,89 @@ mod tests
         assert!(creature::creature_stats(&g).is_some());
         assert_eq!(creature::creature_stats(&g).unwrap().power, 2);
 
-        // remove creature type (doesn't automatically remove fragment)
+        // removing the creature type takes the fragment with it
         g.remove_type(CardType::Creature);
         assert!(!g.is_type(CardType::Creature));
-
-        // fragment still present until explicitly removed
-        creature::remove_creature_fragment(&mut g);
         assert!(!creature::is_creature(&g));
         assert!(creature::creature_stats(&g).is_none());
 
-        // add creature type back and set creature fragment
+        // adding it back installs a 0/0 until stats are set
         g.add_type(CardType::Creature);
+        assert_eq!(creature::creature_stats(&g).unwrap().power, 0);
         creature::add_creature_fragment(&mut g, 3, 3);
         assert!(creature::is_creature(&g));
         assert_eq!(creature::creature_stats(&g).unwrap().power, 3);
+
+        // and the fragment helpers keep the type in step
+        let mut f = forest();
+        creature::add_creature_fragment(&mut f, 1, 1);
+        assert!(f.is_type(CardType::Creature) && f.is_type(CardType::Land));
+        creature::remove_creature_fragment(&mut f);
+        assert_eq!(f.card_types(), [CardType::Land]);
+    }
+
+    #[test]
+    fn every_type_keeps_its_fragments_valid()
+    {
+        for name in ["Forest", "Grizzly Bears", "Llanowar Elves"]
+        {
+            assert_eq!(card_by_name(name).unwrap().validate(), Ok(()));
+        }
+
+        for card_type in CardType::ALL
+        {
+            let mut card = Card::new("Blank", 0);
+            card.add_type(card_type);
+            assert_eq!(card.validate(), Ok(()), "{:?}", card_type);
+            for &fragment in card_type.required_fragments()
+            {
+                assert!(card.fragments().names().any(|n| n == fragment), "{:?} lacks {}", card_type, fragment);
+            }
+
+            card.remove_type(card_type);
+            assert_eq!(card.validate(), Ok(()), "{:?}", card_type);
+            assert!(card.fragments().names().all(|n| CardType::owning(n) != Some(card_type)));
+        }
+
+        // Fragments can't be added or taken away behind a type's back
+        let mut land = forest();
+        assert_eq!(land.insert(CreatureFragment { stats: CreatureStats { power: 1, toughness: 1 }, summoning_sickness: false }), Err(CardError::FragmentWithoutType
+        {
+            card: String::from("Forest"),
+            fragment: CreatureFragment::NAME,
+            card_type: CardType::Creature,
+        }));
+        assert!(land.remove::<TappableFragment>().is_none());
+        assert_eq!(land.validate(), Ok(()));
+    }
+
+    #[test]
+    fn saved_cards_are_checked_as_they_load()
+    {
+        let bears = serde_json::to_value(grizzly_bears()).unwrap();
+        assert!(serde_json::from_value::<Card>(bears.clone()).is_ok());
+
+        let mut typeless = bears.clone();
+        typeless["card_types"] = serde_json::json!([]);
+        let error = serde_json::from_value::<Card>(typeless).err().unwrap();
+        assert!(error.to_string().contains("Grizzly Bears has a Creature fragment but is not a Creature"), "{}", error);
+
+        let mut untappable = bears;
+        untappable["fragments"].as_object_mut().unwrap().remove(TappableFragment::NAME);
+        let error = serde_json::from_value::<Card>(untappable).err().unwrap();
+        assert!(error.to_string().contains("Grizzly Bears is a Creature without a Tappable fragment"), "{}", error);
+    }
+
+    #[test]
+    fn decks_round_trip_through_counts()
+    {
+        let counts = Deck::example().counts();
+        assert_eq!(counts["Forest"], 29);
+        assert_eq!(counts["Grizzly Bears"], 31);
+        assert_eq!(Deck::from_counts(&counts).unwrap().cards.len(), 60);
+
+        let mut bad = counts.clone();
+        bad.insert(String::from("Black Lotus"), 1);
+        assert_eq!(Deck::from_counts(&bad).err(), Some(UnknownCard(String::from("Black Lotus"))));
     }
 }
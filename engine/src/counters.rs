@@ -0,0 +1,128 @@
+use crate::card::{Card, CounterKind, CountersFragment, CreatureStats};
+use crate::game::GameState;
+use crate::rules::RulesError;
+
+pub fn counter_count(card: &Card, kind: &CounterKind) -> u32
+{
+    card.get::<CountersFragment>().and_then(|cf| cf.counters.get(kind).copied()).unwrap_or(0)
+}
+
+/// Put `count` counters of `kind` on the card. +1/+1 and -1/-1 counters cancel each other out.
+pub fn add_counters(card: &mut Card, kind: CounterKind, count: u32)
+{
+    if count == 0
+    {
+        return;
+    }
+
+    let cf = card.builtin_or_default::<CountersFragment>();
+    *cf.counters.entry(kind).or_insert(0) += count;
+
+    let plus = cf.counters.get(&CounterKind::PlusOne).copied().unwrap_or(0);
+    let minus = cf.counters.get(&CounterKind::MinusOne).copied().unwrap_or(0);
+    let cancelled = plus.min(minus);
+    if cancelled > 0
+    {
+        *cf.counters.get_mut(&CounterKind::PlusOne).unwrap() -= cancelled;
+        *cf.counters.get_mut(&CounterKind::MinusOne).unwrap() -= cancelled;
+        cf.counters.retain(|_, n| *n > 0);
+    }
+}
+
+/// Take up to `count` counters of `kind` off the card and return how many were removed.
+pub fn remove_counters(card: &mut Card, kind: &CounterKind, count: u32) -> u32
+{
+    let Some(cf) = card.get_mut::<CountersFragment>() else { return 0 };
+
+    let Some(current) = cf.counters.get_mut(kind) else { return 0 };
+    let removed = count.min(*current);
+    *current -= removed;
+    cf.counters.retain(|_, n| *n > 0);
+    if cf.counters.is_empty()
+    {
+        card.remove::<CountersFragment>();
+    }
+    removed
+}
+
+/// Power and toughness after +1/+1 and -1/-1 counters, never below 0.
+pub fn stats_with_counters(card: &Card) -> Option<CreatureStats>
+{
+    let stats = crate::creature::creature_stats(card)?;
+    let delta = counter_stat_change(card);
+    let adjust = |value: u8| (value as i32 + delta).clamp(0, u8::MAX as i32) as u8;
+    Some(CreatureStats { power: adjust(stats.power), toughness: adjust(stats.toughness) })
+}
+
+/// What +1/+1 and -1/-1 counters add to both power and toughness.
+pub fn counter_stat_change(card: &Card) -> i32
+{
+    counter_count(card, &CounterKind::PlusOne) as i32 - counter_count(card, &CounterKind::MinusOne) as i32
+}
+
+/// Counters on the card for display, e.g. "+1/+1 x2, loyalty x3".
+pub fn describe_counters(card: &Card) -> Option<String>
+{
+    let cf = card.get::<CountersFragment>()?;
+    let parts: Vec<String> = cf.counters.iter().map(|(kind, n)| format!("{} x{}", kind, n)).collect();
+    (!parts.is_empty()).then(|| parts.join(", "))
+}
+
+impl GameState
+{
+    /// Put counters on the permanent at `index` on `player`'s battlefield. A creature whose
+    /// toughness drops to 0 dies.
+    pub fn put_counters(&mut self, player: usize, index: usize, kind: CounterKind, count: u32) -> Result<(), RulesError>
+    {
+        add_counters(self.permanent_mut(player, index)?, kind, count);
+        self.check_zero_toughness();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::card::{Deck, grizzly_bears};
+    use crate::game::Zone;
+
+    #[test]
+    fn plus_and_minus_counters_annihilate()
+    {
+        let mut bears = grizzly_bears();
+        add_counters(&mut bears, CounterKind::PlusOne, 3);
+        add_counters(&mut bears, CounterKind::MinusOne, 1);
+        add_counters(&mut bears, CounterKind::Named(String::from("charge")), 2);
+
+        assert_eq!(counter_count(&bears, &CounterKind::PlusOne), 2);
+        assert_eq!(counter_count(&bears, &CounterKind::MinusOne), 0);
+        assert_eq!(stats_with_counters(&bears).unwrap().power, 4);
+        assert_eq!(describe_counters(&bears).unwrap(), "+1/+1 x2, charge x2");
+
+        // Counters survive a serde round trip under their printed names
+        let json = serde_json::to_string(&bears).unwrap();
+        assert!(json.contains("\"+1/+1\":2"));
+        let back: Card = serde_json::from_str(&json).unwrap();
+        assert_eq!(counter_count(&back, &CounterKind::Named(String::from("charge"))), 2);
+
+        assert_eq!(remove_counters(&mut bears, &CounterKind::PlusOne, 5), 2);
+        assert_eq!(remove_counters(&mut bears, &CounterKind::Named(String::from("charge")), 2), 2);
+        assert!(!bears.fragments().contains::<CountersFragment>());
+    }
+
+    #[test]
+    fn minus_counters_kill_at_zero_toughness()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        gs.players[0].zones.insert(Zone::Battlefield, vec![grizzly_bears()]);
+
+        gs.put_counters(0, 0, CounterKind::MinusOne, 1).unwrap();
+        assert_eq!(stats_with_counters(&gs.players[0].zones[&Zone::Battlefield][0]).unwrap().toughness, 1);
+
+        gs.put_counters(0, 0, CounterKind::MinusOne, 1).unwrap();
+        assert!(gs.players[0].zones[&Zone::Battlefield].is_empty());
+        assert_eq!(gs.players[0].zones[&Zone::Graveyard].len(), 1);
+        assert!(gs.put_counters(0, 0, CounterKind::Loyalty, 1).is_err());
+    }
+}
@@ -1,45 +1,37 @@
-use crate::card::{Card, CardType, CardFragmentKind, CreatureFragment, CreatureStats};
+use crate::card::{Card, CardType, CreatureFragment, CreatureStats};
 
 pub fn is_creature(card: &Card) -> bool
 {
-    card.card_types.iter().any(|ct| *ct == CardType::Creature)
-        || card.fragments.contains_key(&CardFragmentKind::Creature)
+    card.is_type(CardType::Creature)
 }
 
 pub fn creature_stats(card: &Card) -> Option<CreatureStats>
 {
-    card.fragments.get(&CardFragmentKind::Creature).and_then(|f|
-        f.as_any().downcast_ref::<CreatureFragment>().map(|cf| cf.stats)
-    )
+    card.get::<CreatureFragment>().map(|cf| cf.stats)
 }
 
+/// Make the card a creature with these stats, adding the type if it lacks it.
 pub fn add_creature_fragment(card: &mut Card, power: u8, toughness: u8)
 {
-    card.fragments.insert(
-        CardFragmentKind::Creature,
-        Box::new(CreatureFragment { stats: CreatureStats { power, toughness }, summoning_sickness: false }),
-    );
+    card.add_type(CardType::Creature);
+    card.set_builtin(CreatureFragment { stats: CreatureStats { power, toughness }, summoning_sickness: false });
 }
 
+/// Stop the card being a creature: the type goes with the fragment.
 pub fn remove_creature_fragment(card: &mut Card)
 {
-    card.fragments.remove(&CardFragmentKind::Creature);
+    card.remove_type(CardType::Creature);
 }
 
 pub fn set_summoning_sickness(card: &mut Card, value: bool)
 {
-    if let Some(f) = card.fragments.get_mut(&CardFragmentKind::Creature)
+    if let Some(cf) = card.get_mut::<CreatureFragment>()
     {
-        if let Some(cf) = f.as_any_mut().downcast_mut::<CreatureFragment>()
-        {
-            cf.summoning_sickness = value;
-        }
+        cf.summoning_sickness = value;
     }
 }
 
 pub fn has_summoning_sickness(card: &Card) -> bool
 {
-    card.fragments.get(&CardFragmentKind::Creature)
-        .and_then(|f| f.as_any().downcast_ref::<CreatureFragment>().map(|cf| cf.summoning_sickness))
-        .unwrap_or(false)
+    card.get::<CreatureFragment>().is_some_and(|cf| cf.summoning_sickness)
 }
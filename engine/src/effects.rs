@@ -0,0 +1,221 @@
+use serde::{Serialize, Deserialize};
+
+use crate::card::{Card, CardId, CardType, CreatureStats};
+use crate::game::{GameState, Zone};
+
+pub type EffectId = u64;
+
+/// How long a continuous effect lasts.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EffectDuration
+{
+    UntilEndOfTurn,
+    /// Ends once the effect's source has left the battlefield
+    WhileSourceOnBattlefield,
+    Permanent,
+}
+
+/// Which permanents an effect applies to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Affected
+{
+    Card(CardId),
+    CreaturesControlledBy(usize),
+    AllCreatures,
+}
+
+/// What an effect changes. Applied in layer order: type changes, then effects that set power and
+/// toughness, then effects (and counters) that raise or lower them.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Modification
+{
+    AddType(CardType),
+    RemoveType(CardType),
+    SetStats(CreatureStats),
+    ModifyStats { power: i32, toughness: i32 },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContinuousEffect
+{
+    pub id: EffectId,
+    pub source: Option<CardId>,
+    pub affected: Affected,
+    pub modification: Modification,
+    pub duration: EffectDuration,
+}
+
+impl GameState
+{
+    /// Start a continuous effect and return its id. Effects added later apply later within a layer.
+    pub fn add_effect(&mut self, source: Option<CardId>, affected: Affected, modification: Modification, duration: EffectDuration) -> EffectId
+    {
+        let id = self.effects.iter().map(|e| e.id).max().map_or(1, |last| last + 1);
+        self.effects.push(ContinuousEffect { id, source, affected, modification, duration });
+        self.check_zero_toughness();
+        id
+    }
+
+    pub fn remove_effect(&mut self, id: EffectId)
+    {
+        self.effects.retain(|e| e.id != id);
+    }
+
+    /// Drop "until end of turn" effects and any whose source is gone. Called as each turn ends.
+    pub fn end_turn_effects(&mut self)
+    {
+        let expired: Vec<EffectId> = self.effects.iter()
+            .filter(|e| e.duration == EffectDuration::UntilEndOfTurn || !self.effect_is_active(e))
+            .map(|e| e.id)
+            .collect();
+        self.effects.retain(|e| !expired.contains(&e.id));
+    }
+
+    /// The player whose battlefield the card is on, if it is on one.
+    pub fn controller_of(&self, card: CardId) -> Option<usize>
+    {
+        self.players.iter().position(|p| p.zones.get(&Zone::Battlefield).is_some_and(|bf| bf.iter().any(|c| c.id == card)))
+    }
+
+    fn effect_is_active(&self, effect: &ContinuousEffect) -> bool
+    {
+        effect.duration != EffectDuration::WhileSourceOnBattlefield
+            || effect.source.is_some_and(|source| self.controller_of(source).is_some())
+    }
+
+    fn effect_applies(&self, effect: &ContinuousEffect, card: &Card, is_creature: bool) -> bool
+    {
+        self.effect_is_active(effect) && match effect.affected
+        {
+            Affected::Card(id) => card.id == id,
+            Affected::CreaturesControlledBy(player) => is_creature && self.controller_of(card.id) == Some(player),
+            Affected::AllCreatures => is_creature,
+        }
+    }
+
+    /// The card's types once type-changing effects are applied.
+    pub fn effective_types(&self, card: &Card) -> Vec<CardType>
+    {
+        let mut types = card.card_types().to_vec();
+        let printed_creature = types.contains(&CardType::Creature);
+        for effect in self.effects.iter().filter(|e| self.effect_applies(e, card, printed_creature))
+        {
+            match effect.modification
+            {
+                Modification::AddType(t) if !types.contains(&t) => types.push(t),
+                Modification::RemoveType(t) => types.retain(|ct| *ct != t),
+                _ => {}
+            }
+        }
+        types
+    }
+
+    pub fn has_effective_type(&self, card: &Card, card_type: CardType) -> bool
+    {
+        self.effective_types(card).contains(&card_type)
+    }
+
+    /// Power and toughness with every effect and counter applied, or None if the card isn't a
+    /// creature right now. Neither goes below 0.
+    pub fn effective_stats(&self, card: &Card) -> Option<CreatureStats>
+    {
+        if !self.has_effective_type(card, CardType::Creature)
+        {
+            return None;
+        }
+
+        let base = crate::creature::creature_stats(card).unwrap_or(CreatureStats { power: 0, toughness: 0 });
+        let (mut power, mut toughness) = (base.power as i32, base.toughness as i32);
+        let applying: Vec<&ContinuousEffect> = self.effects.iter().filter(|e| self.effect_applies(e, card, true)).collect();
+
+        // Effects that set power and toughness, latest last
+        for effect in &applying
+        {
+            if let Modification::SetStats(stats) = effect.modification
+            {
+                (power, toughness) = (stats.power as i32, stats.toughness as i32);
+            }
+        }
+        // Then everything that raises or lowers them
+        for effect in &applying
+        {
+            if let Modification::ModifyStats { power: p, toughness: t } = effect.modification
+            {
+                power += p;
+                toughness += t;
+            }
+        }
+        let counters = crate::counters::counter_stat_change(card);
+
+        let clamp = |value: i32| value.clamp(0, u8::MAX as i32) as u8;
+        Some(CreatureStats { power: clamp(power + counters), toughness: clamp(toughness + counters) })
+    }
+
+    /// Creatures with 0 toughness die. Tokens cease to exist rather than reaching the graveyard.
+    pub fn check_zero_toughness(&mut self)
+    {
+        let dying: Vec<CardId> = self.players.iter()
+            .flat_map(|p| p.zones.get(&Zone::Battlefield).unwrap())
+            .filter(|card| self.effective_stats(card).is_some_and(|s| s.toughness == 0))
+            .map(|card| card.id)
+            .collect();
+        for id in dying
+        {
+            let _ = self.move_card(id, Zone::Battlefield, Zone::Graveyard);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::card::{CounterKind, Deck, forest, grizzly_bears};
+
+    #[test]
+    fn layers_apply_set_then_modify_then_counters()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        let mut bears = grizzly_bears();
+        crate::counters::add_counters(&mut bears, CounterKind::PlusOne, 1);
+        let id = bears.id;
+        gs.players[0].zones.insert(Zone::Battlefield, vec![bears, grizzly_bears()]);
+        gs.players[1].zones.insert(Zone::Battlefield, vec![grizzly_bears()]);
+
+        // Anthem for player 0, and the first bear becomes a 0/1 until end of turn
+        gs.add_effect(None, Affected::CreaturesControlledBy(0), Modification::ModifyStats { power: 1, toughness: 1 }, EffectDuration::Permanent);
+        gs.add_effect(None, Affected::Card(id), Modification::SetStats(CreatureStats { power: 0, toughness: 1 }), EffectDuration::UntilEndOfTurn);
+
+        let stats = |gs: &GameState, player: usize, index: usize| gs.effective_stats(&gs.players[player].zones[&Zone::Battlefield][index]).unwrap();
+        assert_eq!(stats(&gs, 0, 0), CreatureStats { power: 2, toughness: 3 });
+        assert_eq!(stats(&gs, 0, 1), CreatureStats { power: 3, toughness: 3 });
+        assert_eq!(stats(&gs, 1, 0), CreatureStats { power: 2, toughness: 2 });
+
+        gs.end_turn_effects();
+        assert_eq!(stats(&gs, 0, 0), CreatureStats { power: 4, toughness: 4 });
+    }
+
+    #[test]
+    fn type_changes_and_source_bound_effects()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        let land = forest();
+        let (land_id, lord) = (land.id, grizzly_bears());
+        let lord_id = lord.id;
+        gs.players[0].zones.insert(Zone::Battlefield, vec![lord, land]);
+
+        // The Forest becomes a 0/0 creature, and the bear keeps it alive with +1/+1 while it stays
+        gs.add_effect(Some(lord_id), Affected::CreaturesControlledBy(0), Modification::ModifyStats { power: 1, toughness: 1 }, EffectDuration::WhileSourceOnBattlefield);
+        gs.add_effect(None, Affected::Card(land_id), Modification::AddType(CardType::Creature), EffectDuration::Permanent);
+        let land = &gs.players[0].zones[&Zone::Battlefield][1];
+        assert!(gs.has_effective_type(land, CardType::Creature));
+        assert_eq!(gs.effective_stats(land), Some(CreatureStats { power: 1, toughness: 1 }));
+
+        // Once the bear is gone the 0/0 Forest dies
+        let bear = gs.players[0].zones.get_mut(&Zone::Battlefield).unwrap().remove(0);
+        gs.players[0].zones.get_mut(&Zone::Graveyard).unwrap().push(bear);
+        gs.check_zero_toughness();
+        assert!(gs.players[0].zones[&Zone::Battlefield].is_empty());
+        assert_eq!(gs.players[0].zones[&Zone::Graveyard].len(), 2);
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
+
+use crate::game::{GameState, GameStep, Zone};
+use crate::strategy::PlayerControl;
+
+/// Something that happened in a game, in the order it happened.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GameEvent
+{
+    GameStarted { players: usize },
+    TurnStarted { player: usize, turn: u32 },
+    StepChanged { player: usize, step: GameStep },
+    CardDrawn { player: usize },
+    LibraryShuffled { player: usize },
+    /// Scry or surveil: how many cards were looked at and how many went back on top
+    LibraryArranged { player: usize, looked_at: usize, kept_on_top: usize },
+    CardDiscarded { player: usize, card: String },
+    LandPlayed { player: usize, card: String },
+    CreatureCast { player: usize, card: String },
+    AttackersDeclared { player: usize, attackers: Vec<usize>, targets: HashMap<usize, usize> },
+    BlockersDeclared { player: usize, blocks: HashMap<usize, usize> },
+    PlayerDamaged { player: usize, amount: i32, life: i32 },
+    CreatureDied { player: usize, card: String },
+    /// A card moved between its owner's zones; a creature dying also gives CreatureDied first
+    CardMoved { player: usize, card: String, from: Zone, to: Zone },
+    TokenCreated { player: usize, card: String },
+    AbilityResolved { player: usize, card: String },
+    /// Every target of the ability had become illegal, so it did nothing
+    AbilityFizzled { player: usize, card: String },
+    /// A seat passed between a human and an AI
+    ControlChanged { player: usize, control: PlayerControl },
+    PlayerEliminated { player: usize },
+    GameOver { turns: u32, winners: Vec<usize> },
+}
+
+impl GameState
+{
+    /// Start or stop keeping events. Off by default so bulk simulations don't pay for them.
+    pub fn set_record_events(&mut self, record: bool)
+    {
+        self.record_events = record;
+        if !record
+        {
+            self.events.clear();
+        }
+    }
+
+    pub fn emit(&mut self, event: GameEvent)
+    {
+        self.collect_triggers(&event);
+        if self.record_events
+        {
+            self.events.push(event);
+        }
+    }
+
+    /// Take every event recorded since the last call.
+    pub fn drain_events(&mut self) -> Vec<GameEvent>
+    {
+        std::mem::take(&mut self.events)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::card::{Deck, forest, grizzly_bears};
+    use crate::game::Zone;
+
+    #[test]
+    fn events_are_only_kept_when_recording()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: vec![forest(); 10] });
+        gs.step();
+        assert!(gs.drain_events().is_empty());
+
+        gs.set_record_events(true);
+        gs.step(); // Untap
+        assert_eq!(gs.drain_events(), vec![GameEvent::StepChanged { player: 0, step: GameStep::Upkeep }]);
+        assert!(gs.drain_events().is_empty());
+    }
+
+    #[test]
+    fn combat_reports_damage_and_deaths()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        gs.players[0].zones.insert(Zone::Battlefield, vec![grizzly_bears(), grizzly_bears()]);
+        gs.players[1].zones.insert(Zone::Battlefield, vec![grizzly_bears()]);
+        gs.step = GameStep::DeclareAttackers;
+        gs.set_record_events(true);
+
+        gs.step();
+        gs.step();
+        gs.step();
+
+        let events = gs.drain_events();
+        assert!(events.contains(&GameEvent::AttackersDeclared {
+            player: 0,
+            attackers: vec![0, 1],
+            targets: HashMap::from([(0, 1), (1, 1)]),
+        }));
+        assert!(events.contains(&GameEvent::PlayerDamaged { player: 1, amount: 2, life: 18 }));
+        assert!(events.contains(&GameEvent::CreatureDied { player: 1, card: String::from("Grizzly Bears") }));
+    }
+}
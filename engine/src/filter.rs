@@ -0,0 +1,492 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+
+use crate::card::{Card, CardType, Keyword};
+use crate::game::{GameState, Zone};
+use crate::targeting::TargetController;
+
+// Zones cards_matching walks, public zones first
+const ZONES: [Zone; 5] = [Zone::Battlefield, Zone::Graveyard, Zone::Exile, Zone::Hand, Zone::Library];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Comparison
+{
+    Less,
+    AtMost,
+    Equal,
+    AtLeast,
+    Greater,
+}
+
+impl Comparison
+{
+    pub fn holds<T: Ord>(self, value: T, against: T) -> bool
+    {
+        match self
+        {
+            Comparison::Less => value < against,
+            Comparison::AtMost => value <= against,
+            Comparison::Equal => value == against,
+            Comparison::AtLeast => value >= against,
+            Comparison::Greater => value > against,
+        }
+    }
+
+    fn symbol(self) -> &'static str
+    {
+        match self
+        {
+            Comparison::Less => "<",
+            Comparison::AtMost => "<=",
+            Comparison::Equal => "=",
+            Comparison::AtLeast => ">=",
+            Comparison::Greater => ">",
+        }
+    }
+}
+
+/// A test on a card where it currently is, seen by one player. "Untapped creatures you control
+/// with power 3 or more" is `creature yours battlefield untapped power>=3` as text, or
+/// `CardFilter::Type(Creature).and(CardFilter::Controller(You)).and(!CardFilter::Tapped)...`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CardFilter
+{
+    Any,
+    /// Has the type once type-changing effects are applied
+    Type(CardType),
+    Name(String),
+    /// Whose battlefield, hand or other zone the card is in, relative to the player asking
+    Controller(TargetController),
+    Zone(Zone),
+    Tapped,
+    /// Power and toughness as effective_stats gives them; never matches a noncreature
+    Power(Comparison, i32),
+    Toughness(Comparison, i32),
+    ManaValue(Comparison, u32),
+    Keyword(Keyword),
+    Not(Box<CardFilter>),
+    All(Vec<CardFilter>),
+    AnyOf(Vec<CardFilter>),
+}
+
+impl CardFilter
+{
+    pub fn and(self, other: CardFilter) -> Self
+    {
+        match self
+        {
+            CardFilter::All(mut filters) => { filters.push(other); CardFilter::All(filters) }
+            CardFilter::Any => other,
+            filter => CardFilter::All(vec![filter, other]),
+        }
+    }
+
+    pub fn or(self, other: CardFilter) -> Self
+    {
+        match self
+        {
+            CardFilter::AnyOf(mut filters) => { filters.push(other); CardFilter::AnyOf(filters) }
+            filter => CardFilter::AnyOf(vec![filter, other]),
+        }
+    }
+}
+
+impl std::ops::Not for CardFilter
+{
+    type Output = CardFilter;
+
+    fn not(self) -> CardFilter
+    {
+        match self
+        {
+            CardFilter::Not(filter) => *filter,
+            filter => CardFilter::Not(Box::new(filter)),
+        }
+    }
+}
+
+impl GameState
+{
+    /// Whether `card`, in `holder`'s `zone`, passes `filter` as `viewer` sees it.
+    pub fn card_matches(&self, filter: &CardFilter, viewer: usize, card: &Card, holder: usize, zone: Zone) -> bool
+    {
+        let stat = |comparison: Comparison, against: i32, toughness: bool| self.effective_stats(card).is_some_and(|s|
+        {
+            let value = if toughness { s.toughness } else { s.power };
+            comparison.holds(value as i32, against)
+        });
+        match filter
+        {
+            CardFilter::Any => true,
+            CardFilter::Type(t) => self.has_effective_type(card, *t),
+            CardFilter::Name(name) => card.name.eq_ignore_ascii_case(name),
+            CardFilter::Controller(controller) => match controller
+            {
+                TargetController::Any => true,
+                TargetController::You => holder == viewer,
+                TargetController::Opponent => self.team_of(holder) != self.team_of(viewer),
+            },
+            CardFilter::Zone(z) => zone == *z,
+            CardFilter::Tapped => crate::tappable::is_tapped(card),
+            CardFilter::Power(comparison, against) => stat(*comparison, *against, false),
+            CardFilter::Toughness(comparison, against) => stat(*comparison, *against, true),
+            CardFilter::ManaValue(comparison, against) => comparison.holds(card.cost, *against),
+            CardFilter::Keyword(keyword) => crate::keywords::has_keyword(card, *keyword),
+            CardFilter::Not(filter) => !self.card_matches(filter, viewer, card, holder, zone),
+            CardFilter::All(filters) => filters.iter().all(|f| self.card_matches(f, viewer, card, holder, zone)),
+            CardFilter::AnyOf(filters) => filters.iter().any(|f| self.card_matches(f, viewer, card, holder, zone)),
+        }
+    }
+
+    /// Every card in the game that passes `filter` for `viewer`, with its holder and zone.
+    /// Hidden zones are included, so callers showing this to a player must filter them out.
+    pub fn cards_matching(&self, filter: &CardFilter, viewer: usize) -> Vec<(usize, Zone, &Card)>
+    {
+        ZONES.iter()
+            .flat_map(|&zone| self.players.iter().enumerate()
+                .flat_map(move |(holder, p)| p.zones.get(&zone).into_iter().flatten().map(move |card| (holder, zone, card))))
+            .filter(|&(holder, zone, card)| self.card_matches(filter, viewer, card, holder, zone))
+            .collect()
+    }
+
+    pub fn count_matching(&self, filter: &CardFilter, viewer: usize) -> usize
+    {
+        self.cards_matching(filter, viewer).len()
+    }
+}
+
+/// Why a filter's text couldn't be read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FilterError(pub String);
+
+impl fmt::Display for FilterError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "bad card filter: {}", self.0)
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+fn card_type_name(card_type: CardType) -> &'static str
+{
+    match card_type
+    {
+        CardType::Land => "land",
+        CardType::Creature => "creature",
+    }
+}
+
+fn parse_card_type(word: &str) -> Option<CardType>
+{
+    match word.strip_suffix('s').unwrap_or(word)
+    {
+        "land" => Some(CardType::Land),
+        "creature" => Some(CardType::Creature),
+        _ => None,
+    }
+}
+
+fn zone_name(zone: Zone) -> &'static str
+{
+    match zone
+    {
+        Zone::Hand => "hand",
+        Zone::Battlefield => "battlefield",
+        Zone::Library => "library",
+        Zone::Graveyard => "graveyard",
+        Zone::Exile => "exile",
+    }
+}
+
+// Split into words, quoted names and parentheses
+fn tokenize(text: &str) -> Vec<String>
+{
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek()
+    {
+        if c.is_whitespace()
+        {
+            chars.next();
+        }
+        else if c == '(' || c == ')'
+        {
+            tokens.push(c.to_string());
+            chars.next();
+        }
+        else
+        {
+            let mut token = String::new();
+            while let Some(&c) = chars.peek()
+            {
+                if c == '"'
+                {
+                    chars.next();
+                    let quoted: String = chars.by_ref().take_while(|&c| c != '"').collect();
+                    token.push_str(&quoted);
+                }
+                else if c.is_whitespace() || c == '(' || c == ')'
+                {
+                    break;
+                }
+                else
+                {
+                    token.push(c);
+                    chars.next();
+                }
+            }
+            tokens.push(token);
+        }
+    }
+    tokens
+}
+
+struct Parser
+{
+    tokens: Vec<String>,
+    position: usize,
+}
+
+impl Parser
+{
+    fn peek(&self) -> Option<&str>
+    {
+        self.tokens.get(self.position).map(|t| t.as_str())
+    }
+
+    // query := conjunction ("or" conjunction)*
+    fn query(&mut self) -> Result<CardFilter, FilterError>
+    {
+        let mut alternatives = vec![self.conjunction()?];
+        while self.peek().is_some_and(|t| t.eq_ignore_ascii_case("or"))
+        {
+            self.position += 1;
+            alternatives.push(self.conjunction()?);
+        }
+        Ok(if alternatives.len() == 1 { alternatives.pop().unwrap() } else { CardFilter::AnyOf(alternatives) })
+    }
+
+    // conjunction := term+
+    fn conjunction(&mut self) -> Result<CardFilter, FilterError>
+    {
+        let mut terms = Vec::new();
+        while let Some(token) = self.peek()
+        {
+            if token == ")" || token.eq_ignore_ascii_case("or")
+            {
+                break;
+            }
+            terms.push(self.term()?);
+        }
+        match terms.len()
+        {
+            0 => Err(FilterError(String::from("expected a term"))),
+            1 => Ok(terms.pop().unwrap()),
+            _ => Ok(CardFilter::All(terms)),
+        }
+    }
+
+    // term := "not" term | "(" query ")" | word
+    fn term(&mut self) -> Result<CardFilter, FilterError>
+    {
+        let token = self.peek().ok_or_else(|| FilterError(String::from("expected a term")))?.to_string();
+        self.position += 1;
+        if token.eq_ignore_ascii_case("not")
+        {
+            return Ok(!self.term()?);
+        }
+        if token == "("
+        {
+            let inner = self.query()?;
+            if self.peek() != Some(")")
+            {
+                return Err(FilterError(String::from("missing )")));
+            }
+            self.position += 1;
+            return Ok(inner);
+        }
+        parse_word(&token)
+    }
+}
+
+fn parse_word(token: &str) -> Result<CardFilter, FilterError>
+{
+    let lower = token.to_ascii_lowercase();
+    if let Some(name) = token.strip_prefix("name:")
+    {
+        return Ok(CardFilter::Name(name.to_string()));
+    }
+    if let Some(card_type) = lower.strip_prefix("protection:")
+    {
+        let card_type = parse_card_type(card_type).ok_or_else(|| FilterError(format!("unknown card type in {}", token)))?;
+        return Ok(CardFilter::Keyword(Keyword::ProtectionFrom(card_type)));
+    }
+    for (stat, which) in [("power", 0), ("toughness", 1), ("mv", 2)]
+    {
+        let Some(rest) = lower.strip_prefix(stat) else { continue };
+        let (comparison, number) = [("<=", Comparison::AtMost), (">=", Comparison::AtLeast), ("<", Comparison::Less), (">", Comparison::Greater), ("=", Comparison::Equal)]
+            .into_iter()
+            .find_map(|(symbol, comparison)| rest.strip_prefix(symbol).map(|number| (comparison, number)))
+            .ok_or_else(|| FilterError(format!("expected a comparison in {}", token)))?;
+        let number: i32 = number.parse().map_err(|_| FilterError(format!("expected a number in {}", token)))?;
+        return Ok(match which
+        {
+            0 => CardFilter::Power(comparison, number),
+            1 => CardFilter::Toughness(comparison, number),
+            _ =>
+            {
+                let number = u32::try_from(number).map_err(|_| FilterError(format!("mana value can't be negative in {}", token)))?;
+                CardFilter::ManaValue(comparison, number)
+            }
+        });
+    }
+    if let Some(card_type) = parse_card_type(&lower)
+    {
+        return Ok(CardFilter::Type(card_type));
+    }
+    if let Some(&zone) = ZONES.iter().find(|&&zone| zone_name(zone) == lower)
+    {
+        return Ok(CardFilter::Zone(zone));
+    }
+    match lower.as_str()
+    {
+        "any" | "*" => Ok(CardFilter::Any),
+        "tapped" => Ok(CardFilter::Tapped),
+        "untapped" => Ok(!CardFilter::Tapped),
+        "yours" | "mine" => Ok(CardFilter::Controller(TargetController::You)),
+        "opponents" | "theirs" => Ok(CardFilter::Controller(TargetController::Opponent)),
+        "hexproof" => Ok(CardFilter::Keyword(Keyword::Hexproof)),
+        "shroud" => Ok(CardFilter::Keyword(Keyword::Shroud)),
+        _ => Err(FilterError(format!("unknown term {}", token))),
+    }
+}
+
+impl FromStr for CardFilter
+{
+    type Err = FilterError;
+
+    fn from_str(text: &str) -> Result<Self, FilterError>
+    {
+        let mut parser = Parser { tokens: tokenize(text), position: 0 };
+        if parser.peek().is_none()
+        {
+            return Ok(CardFilter::Any);
+        }
+        let filter = parser.query()?;
+        match parser.peek()
+        {
+            Some(token) => Err(FilterError(format!("unexpected {}", token))),
+            None => Ok(filter),
+        }
+    }
+}
+
+impl CardFilter
+{
+    // Parenthesise alternatives inside anything, and conjunctions inside a "not"
+    fn write(&self, f: &mut fmt::Formatter<'_>, inside: bool, negated: bool) -> fmt::Result
+    {
+        let joined = |f: &mut fmt::Formatter<'_>, filters: &[CardFilter], separator: &str, parens: bool| -> fmt::Result
+        {
+            if parens { write!(f, "(")?; }
+            for (i, filter) in filters.iter().enumerate()
+            {
+                if i > 0 { write!(f, "{}", separator)?; }
+                filter.write(f, true, false)?;
+            }
+            if parens { write!(f, ")")?; }
+            Ok(())
+        };
+        match self
+        {
+            CardFilter::Any => write!(f, "any"),
+            CardFilter::Type(t) => write!(f, "{}", card_type_name(*t)),
+            CardFilter::Name(name) => write!(f, "name:\"{}\"", name),
+            CardFilter::Controller(TargetController::You) => write!(f, "yours"),
+            CardFilter::Controller(TargetController::Opponent) => write!(f, "opponents"),
+            CardFilter::Controller(TargetController::Any) => write!(f, "any"),
+            CardFilter::Zone(zone) => write!(f, "{}", zone_name(*zone)),
+            CardFilter::Tapped => write!(f, "tapped"),
+            CardFilter::Power(c, n) => write!(f, "power{}{}", c.symbol(), n),
+            CardFilter::Toughness(c, n) => write!(f, "toughness{}{}", c.symbol(), n),
+            CardFilter::ManaValue(c, n) => write!(f, "mv{}{}", c.symbol(), n),
+            CardFilter::Keyword(Keyword::Hexproof) => write!(f, "hexproof"),
+            CardFilter::Keyword(Keyword::Shroud) => write!(f, "shroud"),
+            CardFilter::Keyword(Keyword::ProtectionFrom(t)) => write!(f, "protection:{}", card_type_name(*t)),
+            CardFilter::Not(inner) if **inner == CardFilter::Tapped => write!(f, "untapped"),
+            CardFilter::Not(inner) => { write!(f, "not ")?; inner.write(f, true, true) }
+            CardFilter::All(filters) if filters.is_empty() => write!(f, "any"),
+            CardFilter::All(filters) => joined(f, filters, " ", negated),
+            CardFilter::AnyOf(filters) if filters.is_empty() => write!(f, "not any"),
+            CardFilter::AnyOf(filters) => joined(f, filters, " or ", inside),
+        }
+    }
+}
+
+impl fmt::Display for CardFilter
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        self.write(f, false, false)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::card::{Deck, forest, grizzly_bears};
+
+    #[test]
+    fn text_filters_parse_and_print_back()
+    {
+        let filter: CardFilter = "untapped creatures yours battlefield power>=3".parse().unwrap();
+        assert_eq!(filter, CardFilter::All(vec![
+            !CardFilter::Tapped,
+            CardFilter::Type(CardType::Creature),
+            CardFilter::Controller(TargetController::You),
+            CardFilter::Zone(Zone::Battlefield),
+            CardFilter::Power(Comparison::AtLeast, 3),
+        ]));
+
+        for text in ["untapped creature yours battlefield power>=3", "land or name:\"Grizzly Bears\" mv<=2",
+            "not (hexproof or shroud) protection:land", "not (tapped creature) graveyard", "any"]
+        {
+            let filter: CardFilter = text.parse().unwrap();
+            assert_eq!(filter.to_string(), text);
+            assert_eq!(filter.to_string().parse::<CardFilter>().unwrap(), filter);
+        }
+
+        assert_eq!("".parse::<CardFilter>(), Ok(CardFilter::Any));
+        assert!("creature power>>2".parse::<CardFilter>().is_err());
+        assert!("(land".parse::<CardFilter>().is_err());
+        assert!("land not".parse::<CardFilter>().is_err());
+        assert!("wizard".parse::<CardFilter>().is_err());
+        assert!("mv<-1".parse::<CardFilter>().is_err());
+        assert_eq!("power>-1".parse::<CardFilter>(), Ok(CardFilter::Power(Comparison::Greater, -1)));
+    }
+
+    #[test]
+    fn filters_match_cards_where_they_are()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        let mut tapped = grizzly_bears();
+        crate::tappable::set_tapped(&mut tapped, true);
+        gs.players[0].zones.insert(Zone::Battlefield, vec![grizzly_bears(), tapped, forest()]);
+        gs.players[0].zones.insert(Zone::Hand, vec![grizzly_bears()]);
+        gs.players[1].zones.insert(Zone::Battlefield, vec![grizzly_bears()]);
+
+        let count = |text: &str, viewer: usize| gs.count_matching(&text.parse().unwrap(), viewer);
+        assert_eq!(count("untapped creature yours battlefield", 0), 1);
+        assert_eq!(count("creature opponents", 1), 3);
+        assert_eq!(count("creature battlefield power=2 toughness<3", 0), 3);
+        assert_eq!(count("land or hand", 0), 2);
+        // Power needs a creature, so a land never has power 0
+        assert_eq!(count("power<=0", 0), 0);
+        let built = CardFilter::Type(CardType::Creature).and(CardFilter::Zone(Zone::Hand)).or(CardFilter::Tapped);
+        assert_eq!(gs.count_matching(&built, 0), 2);
+    }
+}
@@ -0,0 +1,393 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+
+use crate::card::{AbilitiesFragment, CountersFragment, CreatureFragment, KeywordsFragment, TappableFragment, TriggersFragment};
+
+/// A fragment type cards can carry. `NAME` is the key it is saved under, so it must be unique.
+/// A new fragment implements this and is registered with `register_fragment` at startup; the
+/// engine's own fragments are registered from the start.
+pub trait FragmentType: Any + Clone + Send + Sync + Serialize + DeserializeOwned
+{
+    const NAME: &'static str;
+}
+
+/// A fragment as a card holds it. Implemented for every FragmentType.
+pub trait Fragment: Any + Send + Sync
+{
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+    fn box_clone(&self) -> Box<dyn Fragment>;
+    fn name(&self) -> &'static str;
+    fn to_value(&self) -> Result<serde_json::Value, serde_json::Error>;
+}
+
+impl<T: FragmentType> Fragment for T
+{
+    fn as_any(&self) -> &dyn Any
+    {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any
+    {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any>
+    {
+        self
+    }
+
+    fn box_clone(&self) -> Box<dyn Fragment>
+    {
+        Box::new(self.clone())
+    }
+
+    fn name(&self) -> &'static str
+    {
+        T::NAME
+    }
+
+    fn to_value(&self) -> Result<serde_json::Value, serde_json::Error>
+    {
+        serde_json::to_value(self)
+    }
+}
+
+impl Clone for Box<dyn Fragment>
+{
+    fn clone(&self) -> Box<dyn Fragment>
+    {
+        self.box_clone()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FragmentError
+{
+    /// Saved data names a fragment no type has registered
+    Unknown(String),
+    /// Two fragment types share a name
+    NameTaken(&'static str),
+}
+
+impl std::fmt::Display for FragmentError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            FragmentError::Unknown(name) => write!(f, "unknown fragment: {}", name),
+            FragmentError::NameTaken(name) => write!(f, "fragment name {} is used by another type", name),
+        }
+    }
+}
+
+impl std::error::Error for FragmentError {}
+
+type Loader = fn(serde_json::Value) -> Result<Box<dyn Fragment>, serde_json::Error>;
+
+fn load<T: FragmentType>(value: serde_json::Value) -> Result<Box<dyn Fragment>, serde_json::Error>
+{
+    // Saved before the registry, fragments were wrapped as { "Creature": { ... } }
+    let value = match value
+    {
+        serde_json::Value::Object(mut map) if map.len() == 1 && map.contains_key(T::NAME) => map.remove(T::NAME).unwrap(),
+        value => value,
+    };
+    Ok(Box::new(serde_json::from_value::<T>(value)?))
+}
+
+// Fragment name -> the type registered under it and how to load it
+fn registry() -> &'static RwLock<HashMap<&'static str, (TypeId, Loader)>>
+{
+    static REGISTRY: OnceLock<RwLock<HashMap<&'static str, (TypeId, Loader)>>> = OnceLock::new();
+    REGISTRY.get_or_init(||
+    {
+        let mut map: HashMap<&'static str, (TypeId, Loader)> = HashMap::new();
+        fn entry<T: BuiltinFragment>() -> (&'static str, (TypeId, Loader))
+        {
+            (T::NAME, (TypeId::of::<T>(), load::<T>))
+        }
+        map.extend([
+            entry::<CreatureFragment>(),
+            entry::<TappableFragment>(),
+            entry::<CountersFragment>(),
+            entry::<TriggersFragment>(),
+            entry::<KeywordsFragment>(),
+            entry::<AbilitiesFragment>(),
+        ]);
+        RwLock::new(map)
+    })
+}
+
+/// The engine's own fragments. Their names are registered from the start, so no other type can
+/// take them and adding one to a card can't clash.
+pub(crate) trait BuiltinFragment: FragmentType {}
+
+impl BuiltinFragment for CreatureFragment {}
+impl BuiltinFragment for TappableFragment {}
+impl BuiltinFragment for CountersFragment {}
+impl BuiltinFragment for TriggersFragment {}
+impl BuiltinFragment for KeywordsFragment {}
+impl BuiltinFragment for AbilitiesFragment {}
+
+// The type registered under `name`, if any
+fn registered_type(name: &str) -> Option<TypeId>
+{
+    registry().read().unwrap().get(name).map(|(id, _)| *id)
+}
+
+/// Let saved cards carry a new fragment type `T`. It must be registered before cards carrying it
+/// are loaded; registering the same type again is fine.
+pub fn register_fragment<T: FragmentType>() -> Result<(), FragmentError>
+{
+    let registered = |map: &HashMap<&'static str, (TypeId, Loader)>| map.get(T::NAME).map(|(id, _)| *id == TypeId::of::<T>());
+    match registered(&registry().read().unwrap())
+    {
+        Some(true) => return Ok(()),
+        Some(false) => return Err(FragmentError::NameTaken(T::NAME)),
+        None => {}
+    }
+    let mut map = registry().write().unwrap();
+    match registered(&map)
+    {
+        Some(false) => Err(FragmentError::NameTaken(T::NAME)),
+        _ =>
+        {
+            map.insert(T::NAME, (TypeId::of::<T>(), load::<T>));
+            Ok(())
+        }
+    }
+}
+
+/// A card's fragments, at most one of each type, saved as a map from fragment name to its fields.
+#[derive(Clone, Default)]
+pub struct Fragments(HashMap<&'static str, Box<dyn Fragment>>);
+
+impl Fragments
+{
+    pub fn get<T: FragmentType>(&self) -> Option<&T>
+    {
+        self.0.get(T::NAME).and_then(|f| f.as_any().downcast_ref())
+    }
+
+    pub fn get_mut<T: FragmentType>(&mut self) -> Option<&mut T>
+    {
+        self.0.get_mut(T::NAME).and_then(|f| f.as_any_mut().downcast_mut())
+    }
+
+    /// The card's `T`, added with its default values if it has none.
+    pub fn get_or_default<T: FragmentType + Default>(&mut self) -> Result<&mut T, FragmentError>
+    {
+        if !self.contains::<T>()
+        {
+            self.insert(T::default())?;
+        }
+        Ok(self.get_mut().unwrap())
+    }
+
+    pub fn contains<T: FragmentType>(&self) -> bool
+    {
+        self.get::<T>().is_some()
+    }
+
+    // Add or replace one of the engine's own fragments, which can't clash
+    pub(crate) fn insert_builtin<T: BuiltinFragment>(&mut self, fragment: T)
+    {
+        self.0.insert(T::NAME, Box::new(fragment));
+    }
+
+    /// Add or replace the card's `T`. Fails if another type is registered under `T::NAME` or
+    /// the card holds one under it.
+    pub fn insert<T: FragmentType>(&mut self, fragment: T) -> Result<(), FragmentError>
+    {
+        let taken = registered_type(T::NAME).is_some_and(|id| id != TypeId::of::<T>())
+            || self.0.get(T::NAME).is_some_and(|f| !f.as_any().is::<T>());
+        if taken
+        {
+            return Err(FragmentError::NameTaken(T::NAME));
+        }
+        self.0.insert(T::NAME, Box::new(fragment));
+        Ok(())
+    }
+
+    pub fn remove<T: FragmentType>(&mut self) -> Option<T>
+    {
+        match self.0.get(T::NAME)
+        {
+            Some(f) if f.as_any().is::<T>() => self.0.remove(T::NAME).and_then(|f| f.into_any().downcast().ok()).map(|f| *f),
+            _ => None,
+        }
+    }
+
+    // Drop whatever fragment is saved under `name`
+    pub(crate) fn remove_named(&mut self, name: &str)
+    {
+        self.0.remove(name);
+    }
+
+    /// Copy in every fragment `other` has, replacing any of the same type.
+    pub fn merge(&mut self, other: &Fragments)
+    {
+        self.0.extend(other.0.iter().map(|(name, f)| (*name, f.clone())));
+    }
+
+    /// Names of the fragments present, in no particular order.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_
+    {
+        self.0.keys().copied()
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.0.is_empty()
+    }
+}
+
+impl std::fmt::Debug for Fragments
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        let mut names: Vec<&str> = self.names().collect();
+        names.sort();
+        f.debug_tuple("Fragments").field(&names).finish()
+    }
+}
+
+impl Serialize for Fragments
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        use serde::ser::{Error, SerializeMap};
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, fragment) in &self.0
+        {
+            map.serialize_entry(name, &fragment.to_value().map_err(S::Error::custom)?)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Fragments
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>
+    {
+        use serde::de::Error;
+        let saved: HashMap<String, serde_json::Value> = HashMap::deserialize(deserializer)?;
+        let map = registry().read().unwrap();
+        let mut fragments = HashMap::new();
+        for (name, value) in saved
+        {
+            let (&name, (_, loader)) = map.get_key_value(name.as_str()).ok_or_else(|| D::Error::custom(FragmentError::Unknown(name)))?;
+            fragments.insert(name, loader(value).map_err(D::Error::custom)?);
+        }
+        Ok(Fragments(fragments))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::card::{Card, CreatureStats, grizzly_bears};
+
+    #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct FlyingFragment
+    {
+        height: u32,
+    }
+
+    impl FragmentType for FlyingFragment
+    {
+        const NAME: &'static str = "Flying";
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    struct GlidingFragment;
+
+    impl FragmentType for GlidingFragment
+    {
+        const NAME: &'static str = "Gliding";
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    struct Impostor;
+
+    impl FragmentType for Impostor
+    {
+        const NAME: &'static str = "Creature";
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    struct Fading;
+
+    impl FragmentType for Fading
+    {
+        const NAME: &'static str = "Fading";
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    struct Vanishing;
+
+    impl FragmentType for Vanishing
+    {
+        const NAME: &'static str = "Fading";
+    }
+
+    #[test]
+    fn registered_fragments_load_without_any_card_having_one()
+    {
+        register_fragment::<FlyingFragment>().unwrap();
+
+        // No card in this process has had a Flying fragment yet, and the built-in ones need no registering
+        let saved = r#"{"id":7,"name":"Grizzly Bears","card_types":["Creature"],"cost":2,
+            "fragments":{"Creature":{"stats":{"power":4,"toughness":2},"summoning_sickness":false},"Tappable":{"tapped":false},"Flying":{"height":3}}}"#;
+        let loaded: Card = serde_json::from_str(saved).unwrap();
+        assert_eq!(loaded.get::<FlyingFragment>(), Some(&FlyingFragment { height: 3 }));
+        assert_eq!(loaded.get::<CreatureFragment>().unwrap().stats, CreatureStats { power: 4, toughness: 2 });
+        assert_eq!(loaded.fragments().len(), 3);
+
+        let json = serde_json::to_string(&loaded).unwrap();
+        let again: Card = serde_json::from_str(&json).unwrap();
+        assert_eq!(again.get::<FlyingFragment>(), Some(&FlyingFragment { height: 3 }));
+
+        // Cards saved in the old wrapped form still load
+        let old = r#"{"Tappable":{"Tappable":{"tapped":true}}}"#;
+        let fragments: Fragments = serde_json::from_str(old).unwrap();
+        assert!(fragments.get::<TappableFragment>().unwrap().tapped);
+    }
+
+    #[test]
+    fn unknown_and_clashing_fragments_are_errors()
+    {
+        let error = serde_json::from_str::<Fragments>(r#"{"Phasing":{}}"#).err().unwrap();
+        assert!(error.to_string().contains("unknown fragment: Phasing"));
+
+        // An unregistered fragment saves but fails to load rather than going missing
+        let mut bears = grizzly_bears();
+        bears.insert(GlidingFragment).unwrap();
+        let json = serde_json::to_string(&bears).unwrap();
+        let error = serde_json::from_str::<Card>(&json).err().unwrap();
+        assert!(error.to_string().contains("unknown fragment: Gliding"));
+
+        assert_eq!(register_fragment::<Impostor>(), Err(FragmentError::NameTaken("Creature")));
+        let mut fragments = Fragments::default();
+        assert_eq!(fragments.insert(Impostor), Err(FragmentError::NameTaken("Creature")));
+        fragments.insert(Fading).unwrap();
+        assert_eq!(fragments.insert(Vanishing), Err(FragmentError::NameTaken("Fading")));
+
+        assert_eq!(fragments.remove::<FlyingFragment>(), None);
+        assert!(fragments.remove::<Fading>().is_some());
+        assert!(!fragments.contains::<Fading>());
+    }
+}
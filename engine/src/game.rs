@@ -1,26 +1,45 @@
 use rand::seq::SliceRandom;
-use rand::thread_rng;
-use std::collections::HashMap;
+use rand::{thread_rng, Rng};
+use std::collections::{BTreeMap, HashMap};
 use serde::{Serialize, Deserialize};
 
-use crate::card::{Card, Deck};
-use crate::ELoggingVerbosity;
+use crate::card::{Card, CardId, Deck};
+use crate::strategy::PlayerControl;
+use crate::events::GameEvent;
+use crate::multiplayer::GameOutcome;
+use crate::effects::ContinuousEffect;
+use crate::triggers::StackItem;
+use crate::rules::RulesError;
+use crate::library::LibraryChoice;
 
-#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GameStep 
 {
     StartTurn,
     Untap,
     Upkeep,
     Draw,
-    Main,
+    Main, // precombat main phase
+    BeginCombat,
     DeclareAttackers,
     DeclareBlockers,
     AssignDamage,
+    EndCombat,
+    SecondMain, // postcombat main phase
+    EndStep,
+    Cleanup,
     EndTurn,
     GameOver,
 }
 
+impl GameStep
+{
+    pub fn is_main_phase(self) -> bool
+    {
+        matches!(self, GameStep::Main | GameStep::SecondMain)
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Zone
 {
@@ -59,20 +78,56 @@ impl ProgramState
     }
 }
 
+pub const DEFAULT_MAX_HAND_SIZE: usize = 7;
+
+/// Rules that vary between formats and house rules.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameRules
+{
+    /// Cards a player may keep at cleanup; None for no limit
+    pub max_hand_size: Option<usize>,
+}
+
+impl Default for GameRules
+{
+    fn default() -> Self
+    {
+        GameRules { max_hand_size: Some(DEFAULT_MAX_HAND_SIZE) }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Player
 {
     pub life: i32,
     pub zones: HashMap<Zone, Vec<Card>>,
+    #[serde(default)]
+    pub control: PlayerControl,
+    #[serde(default)]
+    pub eliminated: bool,
+    #[serde(default)]
+    pub mana_pool: u32, // mana made but not yet spent; empties as each step ends
+    #[serde(default)]
+    pub cards_discarded: u32, // discarded to hand size this game
 }
 
 impl Player
 {
     pub fn new(deck: &Deck) -> Self
     {
-        let mut rng = thread_rng();
+        Self::shuffled(deck, &mut thread_rng())
+    }
+
+    /// Shuffle with the given RNG so seeded simulations are repeatable.
+    pub fn shuffled<R: Rng + ?Sized>(deck: &Deck, rng: &mut R) -> Self
+    {
         let mut library = deck.cards.clone();
-        library.shuffle(&mut rng);
+        // Every game gets its own card objects, even when decks share cards
+        for card in library.iter_mut()
+        {
+            card.id = crate::card::next_card_id();
+        }
+        library.shuffle(rng);
 
         let mut hand = Vec::new();
         for _ in 0..7
@@ -94,6 +149,10 @@ impl Player
         {
             life: 20,
             zones,
+            control: PlayerControl::default(),
+            eliminated: false,
+            mana_pool: 0,
+            cards_discarded: 0,
         }
     }
 }
@@ -106,21 +165,58 @@ pub struct GameState
     pub turns: u32,
     pub step: GameStep,
     pub attacking_creatures: Vec<usize>, // indices of creatures on battlefield that are attacking
-    pub blocking_map: HashMap<usize, usize>, // maps blocker index to attacker index
-    pub auto_play: bool, // if false, wait for player decisions; if true, play automatically
+    pub blocking_map: HashMap<usize, usize>, // maps blocker index to attacker index, for the defender who blocked last
+    #[serde(default)]
+    pub attack_targets: HashMap<usize, usize>, // maps attacker index to the player it attacks
+    #[serde(default)]
+    pub blocks: HashMap<usize, HashMap<usize, usize>>, // each defender's blocking map, by defender
+    #[serde(default)]
+    pub defenders_to_block: Vec<usize>, // attacked players still to declare blockers, in turn order
     pub waiting_for_main_decision: bool, // true when waiting for player to decide on playing lands/creatures
     pub waiting_for_attack_decision: bool, // true when waiting for player to declare attackers
     pub waiting_for_block_decision: bool, // true when waiting for player to declare blockers
+    #[serde(default)]
+    pub waiting_for_targets: bool, // true when a human must choose targets for the first pending trigger
+    #[serde(default)]
+    pub waiting_for_discard: bool, // true when waiting for player to discard down to hand size in cleanup
+    #[serde(default)]
+    pub library_choice: Option<LibraryChoice>, // a scry, surveil or search a human must make before resolving goes on
+    pub lands_played_this_turn: u32,
+    #[serde(default)]
+    pub rules: GameRules,
+    #[serde(default)]
+    pub teams: Vec<Vec<usize>>, // player indices of each team, in team turn order; empty for free-for-all
+    #[serde(default)]
+    pub effects: Vec<ContinuousEffect>, // continuous effects, oldest first
+    #[serde(default)]
+    pub pending_triggers: Vec<StackItem>, // triggered but not yet on the stack
+    #[serde(default)]
+    pub stack: Vec<StackItem>, // top of the stack last
+    #[serde(default)]
+    pub outcome: Option<GameOutcome>,
+    #[serde(default)]
+    pub rng_state: u64, // seeds the game's own shuffles; moves on with each one
+    #[serde(skip)]
+    pub events: Vec<GameEvent>, // recorded since the last drain_events()
+    #[serde(skip)]
+    pub record_events: bool,
 }
 
 impl GameState 
 {
     pub fn new(player_count: usize, deck: &Deck) -> Self 
+    {
+        let decks = vec![deck.clone(); player_count.max(2)]; // Minimum 2 players
+        Self::with_decks(&decks, &mut thread_rng())
+    }
+
+    /// One seat per deck, in seat order. A single deck is played by both seats; `decks` must not be empty.
+    pub fn with_decks<R: Rng + ?Sized>(decks: &[Deck], rng: &mut R) -> Self
     {
         let mut players = Vec::new();
-        for _ in 0..player_count.max(2) // Minimum 2 players
+        for seat in 0..decks.len().max(2)
         {
-            players.push(Player::new(deck));
+            players.push(Player::shuffled(&decks[seat.min(decks.len() - 1)], rng));
         }
 
         GameState
@@ -131,10 +227,25 @@ impl GameState
             step: GameStep::StartTurn,
             attacking_creatures: Vec::new(),
             blocking_map: HashMap::new(),
-            auto_play: true, // default to auto-play
+            attack_targets: HashMap::new(),
+            blocks: HashMap::new(),
+            defenders_to_block: Vec::new(),
             waiting_for_main_decision: false,
             waiting_for_attack_decision: false,
             waiting_for_block_decision: false,
+            waiting_for_targets: false,
+            waiting_for_discard: false,
+            library_choice: None,
+            lands_played_this_turn: 0,
+            rules: GameRules::default(),
+            teams: Vec::new(),
+            effects: Vec::new(),
+            pending_triggers: Vec::new(),
+            stack: Vec::new(),
+            outcome: None,
+            rng_state: rng.next_u64(),
+            events: Vec::new(),
+            record_events: false,
         }
     }
 
@@ -151,6 +262,41 @@ impl GameState
         &mut self.players[self.current_player_index]
     }
 
+    // Player who must act in the current step: whoever makes a library choice or chooses pending
+    // targets, the defender declares blockers, otherwise the active player
+    pub fn deciding_player_index(&self) -> usize {
+        if let Some(choice) = &self.library_choice {
+            choice.player()
+        } else if let Some(item) = self.pending_triggers.first().filter(|_| self.waiting_for_targets) {
+            item.controller
+        } else if self.step == GameStep::DeclareBlockers {
+            self.defending_player_index()
+        } else {
+            self.current_player_index
+        }
+    }
+
+    pub fn set_player_control(&mut self, player_index: usize, control: PlayerControl) {
+        if self.players.get(player_index).is_some_and(|p| p.control != control) {
+            self.players[player_index].control = control;
+            self.emit(GameEvent::ControlChanged { player: player_index, control });
+        }
+    }
+
+    // True if any pending decision belongs to a human seat
+    pub fn is_waiting_for_decision(&self) -> bool {
+        self.waiting_for_main_decision || self.waiting_for_attack_decision || self.waiting_for_block_decision
+            || self.waiting_for_targets || self.waiting_for_discard || self.library_choice.is_some()
+    }
+
+    // The defender now declaring blockers; outside of blocks, the first opponent in turn order
+    pub fn defending_player_index(&self) -> usize {
+        match self.defenders_to_block.first() {
+            Some(&defender) if self.step == GameStep::DeclareBlockers => defender,
+            _ => self.default_defender(),
+        }
+    }
+
     pub fn other_players(&self) -> Vec<&Player> {
         self.players.iter().enumerate()
             .filter(|(i, _)| *i != self.current_player_index)
@@ -175,6 +321,58 @@ impl GameState
         &mut self.current_player_mut().zones
     }
 
+    // The current player's battlefield
+    pub fn battlefield_mut(&mut self) -> &mut Vec<Card> {
+        self.zones_mut().get_mut(&Zone::Battlefield).unwrap()
+    }
+
+    // Move the top card of `player`'s library to their hand; false if the library is empty
+    pub fn draw_card(&mut self, player: usize) -> bool {
+        let Some(card) = self.players[player].zones.get_mut(&Zone::Library).unwrap().pop() else {
+            return false;
+        };
+        self.players[player].zones.get_mut(&Zone::Hand).unwrap().push(card);
+        self.emit(GameEvent::CardDrawn { player });
+        true
+    }
+
+    // Cards `player` holds beyond the maximum hand size
+    pub fn excess_hand_size(&self, player: usize) -> usize {
+        let hand = self.players[player].zones.get(&Zone::Hand).unwrap().len();
+        self.rules.max_hand_size.map_or(0, |max| hand.saturating_sub(max))
+    }
+
+    // Discard the cards at `hand_indices` from `player`'s hand; indices must be valid and distinct
+    pub fn discard_cards(&mut self, player: usize, mut hand_indices: Vec<usize>) {
+        hand_indices.sort_unstable_by(|a, b| b.cmp(a));
+        for index in hand_indices {
+            let card = self.players[player].zones.get_mut(&Zone::Hand).unwrap().remove(index);
+            self.emit(GameEvent::CardDiscarded { player, card: card.name.clone() });
+            self.players[player].zones.get_mut(&Zone::Graveyard).unwrap().push(card);
+            self.players[player].cards_discarded += 1;
+        }
+    }
+
+    // The card at `index` on `player`'s battlefield
+    pub fn permanent(&self, player: usize, index: usize) -> Result<&Card, RulesError> {
+        self.players.get(player)
+            .and_then(|p| p.zones.get(&Zone::Battlefield))
+            .and_then(|battlefield| battlefield.get(index))
+            .ok_or(RulesError::InvalidIndex(index))
+    }
+
+    pub fn permanent_mut(&mut self, player: usize, index: usize) -> Result<&mut Card, RulesError> {
+        self.players.get_mut(player)
+            .and_then(|p| p.zones.get_mut(&Zone::Battlefield))
+            .and_then(|battlefield| battlefield.get_mut(index))
+            .ok_or(RulesError::InvalidIndex(index))
+    }
+
+    // Mana the current player has floating or could make from untapped lands and mana abilities
+    pub fn available_mana(&self) -> u32 {
+        self.mana_available_to(self.current_player_index, None)
+    }
+
     pub fn life(&self) -> i32 {
         self.current_player().life
     }
@@ -187,26 +385,75 @@ impl GameState
 impl GameState 
 {
     pub fn step(&mut self)
+    {
+        // Still waiting on a library choice: make it as the seat's strategy would
+        if self.library_choice.is_some()
+        {
+            self.make_default_library_choice();
+            self.resolve_triggers();
+            return;
+        }
+        // Still waiting on targets: try again, in case an AI has taken over the seat
+        if self.waiting_for_targets
+        {
+            self.waiting_for_targets = false;
+            self.resolve_triggers();
+            return;
+        }
+
+        let before = self.step;
+        self.advance_step();
+        self.finish_step(before);
+    }
+
+    /// Whatever follows the game moving on from `before`, by step() or a player's action: unspent
+    /// mana empties, the new step is announced so "at the beginning of" abilities trigger, pending
+    /// triggers resolve and a finished game is reported.
+    pub(crate) fn finish_step(&mut self, before: GameStep)
+    {
+        if self.step != before
+        {
+            self.empty_mana_pools();
+        }
+        if self.step != before && self.step != GameStep::GameOver
+        {
+            self.emit(GameEvent::StepChanged { player: self.current_player_index, step: self.step });
+        }
+
+        // Anything that triggered during the step resolves before the game moves on
+        self.resolve_triggers();
+
+        if self.step != before && self.step == GameStep::GameOver
+        {
+            let winners = self.outcome.as_ref().map(|o| o.winners.clone()).unwrap_or_default();
+            self.emit(GameEvent::GameOver { turns: self.turns, winners });
+        }
+    }
+
+    fn advance_step(&mut self)
     {
         match self.step
         {
             GameStep::StartTurn =>
             {
-                self.turns += 1;
+                // Teammates' turns count as one team turn
+                if self.starts_team_turn(self.current_player_index)
+                {
+                    self.turns += 1;
+                }
+                self.lands_played_this_turn = 0;
+                self.emit(GameEvent::TurnStarted { player: self.current_player_index, turn: self.turns });
                 self.step = GameStep::Untap;
             }
 
             GameStep::Untap =>
             {
                 // Untap all tappable cards
+                for card in self.battlefield_mut()
                 {
-                    let battlefield = self.zones_mut().get_mut(&Zone::Battlefield).unwrap();
-                    for card in battlefield.iter_mut()
+                    if crate::tappable::is_tapped(card)
                     {
-                        if crate::tappable::is_tapped(card)
-                        {
-                            crate::tappable::set_tapped(card, false);
-                        }
+                        crate::tappable::set_tapped(card, false);
                     }
                 }
 
@@ -216,8 +463,7 @@ impl GameState
             GameStep::Upkeep =>
             {
                 // Remove summoning sickness from creatures that have it
-                let battlefield = self.zones_mut().get_mut(&Zone::Battlefield).unwrap();
-                for card in battlefield.iter_mut()
+                for card in self.battlefield_mut()
                 {
                     crate::creature::set_summoning_sickness(card, false);
                 }
@@ -227,212 +473,116 @@ impl GameState
 
             GameStep::Draw =>
             {
-                let card = 
-                {
-                    let library = self.zones_mut().get_mut(&Zone::Library).unwrap();
-                    library.pop()
-                };
-
-                if let Some(card) = card 
+                if self.draw_card(self.current_player_index)
                 {
-                    let hand = self.zones_mut().get_mut(&Zone::Hand).unwrap();
-                    hand.push(card);
                     self.step = GameStep::Main;
                 } 
                 else 
                 {
-                    self.step = GameStep::GameOver;
+                    // Drawing from an empty library loses the game
+                    self.eliminate(self.current_player_index);
+                    self.check_eliminations();
+                    self.end_turn_early();
                 }
             }
 
-            GameStep::Main =>
+            GameStep::Main | GameStep::SecondMain =>
             {
-                if self.auto_play {
-                    // Play up to one land
+                let precombat = self.step == GameStep::Main;
+                match self.current_player().control
+                {
+                    PlayerControl::Ai(strategy) =>
                     {
-                        let card_option =
-                        {
-                            let hand = self.zones_mut().get_mut(&Zone::Hand).unwrap();
-                            if let Some(pos) = hand.iter().position(|c| c.is_type(crate::card::CardType::Land))
-                            {
-                                Some(hand.remove(pos))  // hand borrow ends here
-                            }
-                            else
-                            {
-                                None
-                            }
-                        };
-
-                        if let Some(card) = card_option
+                        // Play up to one land
+                        if let Some(pos) = strategy.choose_land(self)
                         {
-                            let battlefield = self.zones_mut().get_mut(&Zone::Battlefield).unwrap();
-                            battlefield.push(card);
+                            let _ = self.play_land(pos);
                         }
-                    }
-
-                    // Cast as many creatures as possible until there is no more mana
-                    loop
-                    {
-                        // Count available untapped lands as available mana
-                        let available_mana = self.zones().get(&Zone::Battlefield).unwrap().iter().filter(|card| 
-                            card.is_type(crate::card::CardType::Land) && !crate::tappable::is_tapped(card)).count() as u32;
-
-                        // Find first castable creature in hand
-                        let cast_pos = 
-                        {
-                            let hand = self.zones().get(&Zone::Hand).unwrap();
-                            hand.iter().position(|card| crate::creature::is_creature(card) && card.cost <= available_mana)
-                        };
 
-                        if let Some(pos) = cast_pos
+                        // Cast as many creatures as possible until there is no more mana, unless
+                        // the strategy holds them until after combat
+                        while !(precombat && strategy.casts_after_combat())
+                            && let Some(pos) = strategy.choose_cast(self)
                         {
-                            // Remove card first
-                            let mut card = 
-                            {
-                                let hand = self.zones_mut().get_mut(&Zone::Hand).unwrap();
-                                hand.remove(pos)
-                            };
-
-                            vlog!(ELoggingVerbosity::Verbose, "Cast {}", card.name);
-
-                            // Newly cast creatures have summoning sickness
-                            crate::creature::set_summoning_sickness(&mut card, true);
-
-                            // Tap lands to pay for the creature's cost
-                            let mut need = card.cost;
+                            if self.cast_creature(pos).is_err()
                             {
-                                let battlefield = self.zones_mut().get_mut(&Zone::Battlefield).unwrap();
-                                for b in battlefield.iter_mut().filter(|c| c.is_type(crate::card::CardType::Land) && !crate::tappable::is_tapped(c)) 
-                                {
-                                    if need == 0 
-                                    { 
-                                        break; 
-                                    }
-                                    crate::tappable::set_tapped(b, true);
-                                    need -= 1;
-                                }
+                                break;
                             }
-
-                            // Put the card onto the battlefield
-                            let battlefield = self.zones_mut().get_mut(&Zone::Battlefield).unwrap();
-                            battlefield.push(card);
-                        }
-                        else
-                        {
-                            // Nothing more can be cast
-                            break;
                         }
                     }
-                } else if !self.waiting_for_main_decision {
-                    // When not auto-playing, wait for player input
-                    self.waiting_for_main_decision = true;
-                    return;
+                    PlayerControl::Human if !self.waiting_for_main_decision =>
+                    {
+                        // Wait for the player to play lands/creatures
+                        self.waiting_for_main_decision = true;
+                        return;
+                    }
+                    PlayerControl::Human => {}
                 }
 
+                self.waiting_for_main_decision = false;
+                // Unless a triggered ability already ended the turn
+                if self.step.is_main_phase()
+                {
+                    self.step = if precombat { GameStep::BeginCombat } else { GameStep::EndStep };
+                }
+            }
+
+            GameStep::BeginCombat =>
+            {
                 self.step = GameStep::DeclareAttackers;
             }
 
             GameStep::DeclareAttackers =>
             {
-                if self.auto_play {
-                    // Auto-attack: select all untapped creatures without summoning sickness
-                    let attacking_indices = {
-                        let battlefield = self.zones().get(&Zone::Battlefield).unwrap();
-                        let mut indices = Vec::new();
-                        for (i, card) in battlefield.iter().enumerate()
-                        {
-                            if card.is_type(crate::card::CardType::Creature) && 
-                               !crate::creature::has_summoning_sickness(card) && 
-                               !crate::tappable::is_tapped(card)
-                            {
-                                indices.push(i);
-                            }
-                        }
-                        indices
-                    };
-
-                    self.attacking_creatures = attacking_indices;
-
-                    // Tap all attacking creatures
-                    let attacking_to_tap = self.attacking_creatures.clone();
+                let attacks = match self.current_player().control
+                {
+                    PlayerControl::Ai(strategy) => strategy.choose_attacks(self),
+                    PlayerControl::Human if !self.waiting_for_attack_decision =>
                     {
-                        let battlefield = self.zones_mut().get_mut(&Zone::Battlefield).unwrap();
-                        for idx in attacking_to_tap {
-                            if idx < battlefield.len() {
-                                crate::tappable::set_tapped(&mut battlefield[idx], true);
-                            }
-                        }
+                        // Wait for player to declare attackers
+                        self.waiting_for_attack_decision = true;
+                        return;
                     }
-                } else if !self.waiting_for_attack_decision {
-                    // Wait for player to declare attackers
-                    self.waiting_for_attack_decision = true;
-                    return;
-                }
+                    PlayerControl::Human => Vec::new(),
+                };
 
-                self.step = GameStep::DeclareBlockers;
+                // Taps the attackers, clears the waiting flag and moves on to blocks
+                if self.declare_attacks(attacks).is_err()
+                {
+                    let _ = self.declare_attackers(Vec::new());
+                }
             }
 
             GameStep::DeclareBlockers =>
             {
-                if self.auto_play {
-                    // Auto-play blocking: block with creatures that can kill the attacker
-                    self.blocking_map.clear();
-                    
-                    // Collect blocking decisions while holding battlefield borrow
-                    let blocking_decisions = {
-                        let battlefield = self.zones().get(&Zone::Battlefield).unwrap();
-                        let mut used_blockers = std::collections::HashSet::new();
-                        let mut decisions = Vec::new();
-                        
-                        for attacker_idx in &self.attacking_creatures {
-                            if *attacker_idx >= battlefield.len() {
-                                continue;
-                            }
-                            
-                            let attacker_toughness = crate::creature::creature_stats(&battlefield[*attacker_idx])
-                                .map(|stats| stats.toughness as i32)
-                                .unwrap_or(0);
-                            
-                            // Find a blocker that can kill this attacker
-                            for (blocker_idx, blocker_card) in battlefield.iter().enumerate() {
-                                if used_blockers.contains(&blocker_idx) || self.attacking_creatures.contains(&blocker_idx) {
-                                    continue; // Already used or is attacking
-                                }
-                                
-                                let blocker_power = crate::creature::creature_stats(blocker_card)
-                                    .map(|stats| stats.power as i32)
-                                    .unwrap_or(0);
-                                
-                                if blocker_power >= attacker_toughness {
-                                    // This blocker can kill the attacker
-                                    decisions.push((blocker_idx, *attacker_idx));
-                                    used_blockers.insert(blocker_idx);
-                                    break;
-                                }
-                            }
-                        }
-                        
-                        decisions
-                    };
-                    
-                    // Now insert decisions into blocking_map (borrow released)
-                    for (blocker_idx, attacker_idx) in blocking_decisions {
-                        self.blocking_map.insert(blocker_idx, attacker_idx);
+                // Each attacked player declares blocks in turn order, one per step
+                let defender = self.defending_player_index();
+                let blocking_map = match self.players[defender].control
+                {
+                    // Nothing to decide when nobody attacked this player
+                    _ if self.attackers_targeting(defender).is_empty() => HashMap::new(),
+                    PlayerControl::Ai(strategy) => strategy.choose_blockers(self),
+                    PlayerControl::Human if !self.waiting_for_block_decision =>
+                    {
+                        // Wait for player to declare blockers
+                        self.waiting_for_block_decision = true;
+                        return;
                     }
-                } else if !self.waiting_for_block_decision {
-                    // Wait for player to declare blockers
-                    self.waiting_for_block_decision = true;
-                    return;
-                }
-
-                self.step = GameStep::AssignDamage;
+                    PlayerControl::Human => HashMap::new(),
+                };
 
+                if self.declare_blockers(blocking_map).is_err()
+                {
+                    let _ = self.declare_blockers(HashMap::new());
+                }
             }
 
             GameStep::AssignDamage =>
             {
-                let mut creatures_to_destroy = Vec::new();
-                let mut damage_to_apply = 0;
+                let attacker_index = self.current_player_index;
+                let mut attackers_to_destroy = Vec::new();
+                let mut blockers_to_destroy: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+                let mut damage_to_players: BTreeMap<usize, i32> = BTreeMap::new();
                 
                 // First pass: calculate damage
                 {
@@ -443,87 +593,127 @@ impl GameState
                             continue;
                         }
 
-                        let attacker_power = crate::creature::creature_stats(&battlefield[*attacker_idx])
+                        let attacker_power = self.effective_stats(&battlefield[*attacker_idx])
                             .map(|stats| stats.power as i32)
                             .unwrap_or(0);
 
-                        // Check if this attacker is blocked
-                        let blocked_by = self.blocking_map.iter()
-                            .find(|(_, attacker)| **attacker == *attacker_idx)
-                            .map(|(blocker, _)| *blocker);
+                        // Check if the player this attacker went after blocked it
+                        let defender_index = self.attack_target(*attacker_idx);
+                        let blocked_by = self.blocks.get(&defender_index).and_then(|blocks| {
+                            blocks.iter()
+                                .find(|(_, attacker)| **attacker == *attacker_idx)
+                                .map(|(blocker, _)| *blocker)
+                        });
 
                         if let Some(blocker_idx) = blocked_by {
-                            if blocker_idx < battlefield.len() {
+                            // The blocker may be a teammate's creature
+                            if let Some((blocker_player, blocker_idx)) = self.blocker(defender_index, blocker_idx) {
                                 // Attacker and blocker deal damage to each other
-                                let blocker_toughness = crate::creature::creature_stats(&battlefield[blocker_idx])
+                                let blocker = &self.players[blocker_player].zones[&Zone::Battlefield][blocker_idx];
+                                let blocker_toughness = self.effective_stats(blocker)
                                     .map(|stats| stats.toughness as i32)
                                     .unwrap_or(0);
-                                let blocker_power = crate::creature::creature_stats(&battlefield[blocker_idx])
+                                let blocker_power = self.effective_stats(blocker)
                                     .map(|stats| stats.power as i32)
                                     .unwrap_or(0);
 
                                 if attacker_power >= blocker_toughness {
-                                    creatures_to_destroy.push(blocker_idx);
+                                    blockers_to_destroy.entry(blocker_player).or_default().push(blocker_idx);
                                 }
-                                let attacker_toughness = crate::creature::creature_stats(&battlefield[*attacker_idx])
+                                let attacker_toughness = self.effective_stats(&battlefield[*attacker_idx])
                                     .map(|stats| stats.toughness as i32)
                                     .unwrap_or(0);
                                 if blocker_power >= attacker_toughness {
-                                    creatures_to_destroy.push(*attacker_idx);
+                                    attackers_to_destroy.push(*attacker_idx);
                                 }
                             }
                         } else {
-                            // Unblocked: damage goes to opponent's life
-                            damage_to_apply += attacker_power;
+                            // Unblocked: damage goes to the attacked player's life
+                            *damage_to_players.entry(defender_index).or_insert(0) += attacker_power;
                         }
                     }
                 }
 
-                // Apply damage to opponents
-                for other_player in self.other_players_mut() {
-                    other_player.life -= damage_to_apply;
-                }
-
-                // Destroy creatures that took lethal damage
-                creatures_to_destroy.sort_by(|a, b| b.cmp(a)); // Sort reverse to remove from end first
-                creatures_to_destroy.dedup();
-                
-                let destroyed_cards = {
-                    let battlefield = self.zones_mut().get_mut(&Zone::Battlefield).unwrap();
-                    let mut cards = Vec::new();
-                    for idx in creatures_to_destroy {
-                        if idx < battlefield.len() {
-                            cards.push(battlefield.remove(idx));
-                        }
+                // Apply damage to the attacked players
+                for (player, amount) in damage_to_players {
+                    if amount > 0 {
+                        self.lose_life(player, amount);
+                        let life = self.players[player].life;
+                        self.emit(GameEvent::PlayerDamaged { player, amount, life });
                     }
-                    cards
-                };
+                }
 
-                // Move destroyed cards to graveyard
-                {
-                    let graveyard = self.zones_mut().get_mut(&Zone::Graveyard).unwrap();
-                    for card in destroyed_cards {
-                        graveyard.push(card);
-                    }
+                // Destroy creatures that took lethal damage, by id so each removal leaves the other indices alone
+                let destroyed: Vec<CardId> = std::iter::once((attacker_index, attackers_to_destroy)).chain(blockers_to_destroy)
+                    .flat_map(|(player_index, to_destroy)| {
+                        let battlefield = &self.players[player_index].zones[&Zone::Battlefield];
+                        to_destroy.into_iter().filter_map(|idx| battlefield.get(idx).map(|card| card.id)).collect::<Vec<_>>()
+                    })
+                    .collect();
+                for id in destroyed {
+                    self.destroy_permanent(id);
                 }
 
                 // Clear attacking and blocking data
                 self.attacking_creatures.clear();
+                self.attack_targets.clear();
                 self.blocking_map.clear();
+                self.blocks.clear();
+                self.defenders_to_block.clear();
+
+                // Knock out dead players; the game ends when one is left
+                self.check_eliminations();
+                if !self.is_game_over() {
+                    self.step = GameStep::EndCombat;
+                }
+            }
+
+            GameStep::EndCombat =>
+            {
+                self.step = GameStep::SecondMain;
+            }
+
+            GameStep::EndStep =>
+            {
+                self.step = GameStep::Cleanup;
+            }
 
-                // Check if any player has lost
-                let anyone_dead = self.players.iter().any(|p| p.life <= 0);
-                if anyone_dead {
-                    self.step = GameStep::GameOver;
-                } else {
-                    self.step = GameStep::EndTurn;
+            GameStep::Cleanup =>
+            {
+                // Discard down to the maximum hand size
+                let player = self.current_player_index;
+                let excess = self.excess_hand_size(player);
+                if excess > 0 && self.is_alive(player)
+                {
+                    let discards = match self.players[player].control
+                    {
+                        PlayerControl::Ai(strategy) => strategy.choose_discards(self, excess),
+                        PlayerControl::Human if !self.waiting_for_discard =>
+                        {
+                            // Wait for the player to choose what to discard
+                            self.waiting_for_discard = true;
+                            return;
+                        }
+                        // Stepped past the choice: the newest cards go
+                        PlayerControl::Human =>
+                        {
+                            let hand = self.players[player].zones.get(&Zone::Hand).unwrap().len();
+                            (hand - excess..hand).collect()
+                        }
+                    };
+                    self.discard_cards(player, discards);
                 }
+                self.waiting_for_discard = false;
+
+                // "Until end of turn" effects wear off
+                self.end_turn_effects();
+                self.step = GameStep::EndTurn;
             }
 
             GameStep::EndTurn =>
             {
-                // Advance to next player
-                self.current_player_index = (self.current_player_index + 1) % self.players.len();
+                // Advance to the next player still in the game
+                self.current_player_index = self.next_turn_player(self.current_player_index);
                 self.step = GameStep::StartTurn;
             }
 
@@ -534,11 +724,39 @@ impl GameState
         }
     }
 
+    /// Skip to cleanup when the active player is out of the game partway through their turn.
+    pub fn end_turn_early(&mut self)
+    {
+        if !self.is_game_over() && !matches!(self.step, GameStep::Cleanup | GameStep::EndTurn)
+        {
+            self.step = GameStep::Cleanup;
+        }
+    }
+
+    /// Step until a human seat has a decision to make or the game ends.
+    pub fn advance_until_decision(&mut self)
+    {
+        while !self.is_game_over() && !self.is_waiting_for_decision()
+        {
+            self.step();
+        }
+    }
+
     pub fn is_game_over(&self) -> bool
     {
         self.step == GameStep::GameOver
     }
 
+    /// The last player standing, once the game is over.
+    pub fn winner(&self) -> Option<usize>
+    {
+        match self.outcome.as_ref().map(|o| o.winners.as_slice())
+        {
+            Some([winner]) => Some(*winner),
+            _ => None,
+        }
+    }
+
     pub fn describe(&self, verbose: bool)
     {
         println!("Turn: {}", self.turns);
@@ -567,10 +785,10 @@ impl GameState
 
     pub fn describe_verbose(&self)
     {
-        for zone in &[Zone::Hand, Zone::Battlefield, Zone::Library, Zone::Graveyard]
+        for zone in &[Zone::Hand, Zone::Battlefield, Zone::Library, Zone::Graveyard, Zone::Exile]
         {
             let cards = self.zones().get(zone).unwrap();
-            if cards.is_empty() && (*zone == Zone::Battlefield || *zone == Zone::Graveyard)
+            if cards.is_empty() && *zone != Zone::Hand && *zone != Zone::Library
             {
                 continue;
             }
@@ -593,9 +811,9 @@ impl GameState
                         println!("  {} x{}", name, count);
                     }
                 }
-                Zone::Hand =>
+                Zone::Hand | Zone::Graveyard | Zone::Exile =>
                 {
-                    // Print hand cards grouped by count in an inline list
+                    // Print the cards grouped by count in an inline list
                     let mut groups: HashMap<String, u32> = HashMap::new();
                     for card in cards.iter()
                     {
@@ -629,23 +847,30 @@ impl GameState
                     let mut card_groups: HashMap<String, (String, u8, u8, bool, bool, u32)> = HashMap::new();
                     for card in cards.iter()
                     {
-                        let power = crate::creature::creature_stats(card).map(|s| s.power).unwrap_or(0);
-                        let toughness = crate::creature::creature_stats(card).map(|s| s.toughness).unwrap_or(0);
-                        let is_creature = crate::creature::is_creature(card);
+                        let stats = self.effective_stats(card);
+                        let power = stats.map(|s| s.power).unwrap_or(0);
+                        let toughness = stats.map(|s| s.toughness).unwrap_or(0);
+                        let is_creature = stats.is_some();
                         let is_sick = crate::creature::has_summoning_sickness(card);
 
+                        // Cards with different counters are listed separately
+                        let name = match crate::counters::describe_counters(card)
+                        {
+                            Some(counters) => format!("{} [{}]", card.name, counters),
+                            None => card.name.clone(),
+                        };
                         let uniquename = if is_creature && is_sick
                         {
-                            format!("{} (sick)", card.name)
+                            format!("{} (sick)", name)
                         }
                         else
                         {
-                            card.name.clone()
+                            name.clone()
                         };
 
                         card_groups.entry(uniquename)
                             .and_modify(|(_, _, _, _, _, count)| *count += 1)
-                            .or_insert((card.name.clone(), power, toughness, is_creature, is_sick, 1));
+                            .or_insert((name, power, toughness, is_creature, is_sick, 1));
                     }
 
                     for (_uniquename, (name, power, toughness, is_creature, is_sick, count)) in card_groups.iter()
@@ -674,7 +899,6 @@ impl GameState
                         }
                     }
                 }
-                _ => {}
             }
         }
     }
@@ -687,6 +911,18 @@ mod tests
     use crate::card::{grizzly_bears, forest};
     use crate::creature;
 
+    // Two-player game where player 0 is active and has the given zones
+    fn game_with(zones: HashMap<Zone, Vec<Card>>, step: GameStep) -> GameState
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        for (zone, cards) in zones
+        {
+            gs.players[0].zones.insert(zone, cards);
+        }
+        gs.step = step;
+        gs
+    }
+
     #[test]
     fn creature_without_sickness_deals_damage()
     {
@@ -698,13 +934,12 @@ mod tests
 
         let mut zones = std::collections::HashMap::new();
         zones.insert(Zone::Battlefield, battlefield);
-        zones.insert(Zone::Hand, Vec::new());
-        zones.insert(Zone::Library, Vec::new());
-        zones.insert(Zone::Graveyard, Vec::new());
 
-        let mut gs = GameState { zones, life: 20, turns: 0, step: GameStep::Combat };
-        gs.step();
-        assert_eq!(gs.life, 18);
+        let mut gs = game_with(zones, GameStep::DeclareAttackers);
+        gs.step(); // DeclareAttackers -> DeclareBlockers
+        gs.step(); // DeclareBlockers -> AssignDamage
+        gs.step(); // AssignDamage
+        assert_eq!(gs.players[1].life, 18);
     }
 
     #[test]
@@ -717,13 +952,12 @@ mod tests
 
         let mut zones = std::collections::HashMap::new();
         zones.insert(Zone::Battlefield, battlefield);
-        zones.insert(Zone::Hand, Vec::new());
-        zones.insert(Zone::Library, Vec::new());
-        zones.insert(Zone::Graveyard, Vec::new());
 
-        let mut gs = GameState { zones, life: 20, turns: 0, step: GameStep::Combat };
+        let mut gs = game_with(zones, GameStep::DeclareAttackers);
+        gs.step();
         gs.step();
-        assert_eq!(gs.life, 20);
+        gs.step();
+        assert_eq!(gs.players[1].life, 20);
     }
 
     #[test]
@@ -736,33 +970,26 @@ mod tests
 
         let mut zones = std::collections::HashMap::new();
         zones.insert(Zone::Battlefield, battlefield);
-        zones.insert(Zone::Hand, Vec::new());
-        zones.insert(Zone::Library, Vec::new());
-        zones.insert(Zone::Graveyard, Vec::new());
 
-        let mut gs = GameState { zones, life: 20, turns: 0, step: GameStep::Upkeep };
+        let mut gs = game_with(zones, GameStep::Upkeep);
         gs.step();
-        let bf = gs.zones.get(&Zone::Battlefield).unwrap();
+        let bf = gs.zones().get(&Zone::Battlefield).unwrap();
         assert!(!crate::creature::has_summoning_sickness(&bf[0]));
     }
 
     #[test]
     fn play_one_land_if_available()
     {
-        let library = Vec::new();
         let mut hand = Vec::new();
         hand.push(forest());
 
         let mut zones = std::collections::HashMap::new();
-        zones.insert(Zone::Library, library);
         zones.insert(Zone::Hand, hand);
-        zones.insert(Zone::Battlefield, Vec::new());
-        zones.insert(Zone::Graveyard, Vec::new());
 
-        let mut gs = GameState { zones, life: 20, turns: 0, step: GameStep::Main };
+        let mut gs = game_with(zones, GameStep::Main);
         gs.step();
 
-        assert_eq!(gs.zones.get(&Zone::Battlefield).unwrap().len(), 1);
+        assert_eq!(gs.zones().get(&Zone::Battlefield).unwrap().len(), 1);
     }
 
     #[test]
@@ -780,20 +1007,17 @@ mod tests
         }
 
         let mut zones = std::collections::HashMap::new();
-        zones.insert(Zone::Library, Vec::new());
         zones.insert(Zone::Hand, hand);
         zones.insert(Zone::Battlefield, battlefield);
-        zones.insert(Zone::Graveyard, Vec::new());
 
-        let mut gs = GameState { zones, life: 20, turns: 0, step: GameStep::Main };
+        let mut gs = game_with(zones, GameStep::Main);
         gs.step();
 
-        // Only ONE creature should be cast per main phase (4 lands available, but can only cast 1 creature)
-        assert_eq!(gs.zones.get(&Zone::Battlefield).unwrap().len(), 5); // 4 lands + 1 creature
+        // Both creatures are cast with 4 lands available
+        assert_eq!(gs.zones().get(&Zone::Battlefield).unwrap().len(), 6); // 4 lands + 2 creatures
         // Verify we have the 4 lands still on battlefield
-        assert_eq!(gs.zones.get(&Zone::Battlefield).unwrap().iter().filter(|c| c.is_type(crate::card::CardType::Land)).count(), 4);
-        // One creature should be in hand still
-        assert_eq!(gs.zones.get(&Zone::Hand).unwrap().len(), 1);
+        assert_eq!(gs.zones().get(&Zone::Battlefield).unwrap().iter().filter(|c| c.is_type(crate::card::CardType::Land)).count(), 4);
+        assert_eq!(gs.zones().get(&Zone::Hand).unwrap().len(), 0);
     }
 
     #[test]
@@ -818,31 +1042,30 @@ mod tests
         zones.insert(Zone::Library, library);
         zones.insert(Zone::Hand, hand);
         zones.insert(Zone::Battlefield, battlefield);
-        zones.insert(Zone::Graveyard, Vec::new());
 
-        let mut gs = GameState { zones, life: 20, turns: 0, step: GameStep::StartTurn };
+        let mut gs = game_with(zones, GameStep::StartTurn);
+        // The opponent needs cards to draw so the game survives their turn
+        gs.players[1].zones.insert(Zone::Library, vec![forest(), forest()]);
 
-        // Turn 1: StartTurn -> Untap -> Upkeep -> Draw -> Main -> Combat
+        // Turn 1: StartTurn -> Untap -> Upkeep -> Draw -> Main -> BeginCombat -> combat
         gs.step(); // StartTurn -> Untap
         gs.step(); // Untap -> Upkeep
         gs.step(); // Upkeep -> Draw (draws a forest)
         gs.step(); // Draw -> Main
-        gs.step(); // Main -> Combat (plays 1 land, casts grizzly with 2 mana total, gives it summoning sickness)
-        gs.step(); // Combat should NOT deal damage because creature is sick
-        assert_eq!(gs.life, 20, "Creature with summoning sickness should not deal damage on the turn it was cast");
-
-        // Continue to EndTurn -> StartTurn -> Untap -> Upkeep (for turn 2)
-        gs.step(); // Combat -> EndTurn
-        gs.step(); // EndTurn -> StartTurn
-        gs.step(); // StartTurn -> Untap
-        gs.step(); // Untap -> Upkeep (clears sickness)
-
-        // Advance to Combat of second turn
-        gs.step(); // Upkeep -> Draw (draws another forest)
-        gs.step(); // Draw -> Main
-        gs.step(); // Main -> Combat
-        gs.step(); // Combat should now deal damage
-        assert!(gs.life < 20, "Creature should deal damage after sickness cleared on upkeep");
+        gs.step(); // Main -> BeginCombat (plays 1 land, casts grizzly with 2 mana total, gives it summoning sickness)
+        gs.step(); // BeginCombat -> DeclareAttackers
+        gs.step(); // DeclareAttackers -> DeclareBlockers
+        gs.step(); // DeclareBlockers -> AssignDamage
+        gs.step(); // AssignDamage should NOT deal damage because creature is sick
+        assert_eq!(gs.players[1].life, 20, "Creature with summoning sickness should not deal damage on the turn it was cast");
+
+        // Let the opponent's turn play out, then advance to player 0's second combat
+        while !(gs.current_player_index == 0 && gs.step == GameStep::AssignDamage)
+        {
+            gs.step();
+        }
+        gs.step(); // AssignDamage should now deal damage
+        assert!(gs.players[1].life < 20, "Creature should deal damage after sickness cleared on upkeep");
     }
 
     #[test]
@@ -857,16 +1080,14 @@ mod tests
         battlefield.push(forest());
 
         let mut zones = std::collections::HashMap::new();
-        zones.insert(Zone::Library, Vec::new());
         zones.insert(Zone::Hand, hand);
         zones.insert(Zone::Battlefield, battlefield);
-        zones.insert(Zone::Graveyard, Vec::new());
 
-        let mut gs = GameState { zones, life: 20, turns: 0, step: GameStep::Main };
+        let mut gs = game_with(zones, GameStep::Main);
         gs.step();
 
         // After casting, a grizzly should be on the battlefield and two forests should be tapped
-        let bf = gs.zones.get(&Zone::Battlefield).unwrap();
+        let bf = gs.zones().get(&Zone::Battlefield).unwrap();
         assert_eq!(bf.iter().filter(|c| c.is_type(crate::card::CardType::Land)).count(), 2);
         assert_eq!(bf.iter().filter(|c| c.is_type(crate::card::CardType::Creature)).count(), 1);
         let tapped_lands = bf.iter().filter(|c| c.is_type(crate::card::CardType::Land) && crate::tappable::is_tapped(c)).count();
@@ -883,14 +1104,97 @@ mod tests
 
         let mut zones = std::collections::HashMap::new();
         zones.insert(Zone::Battlefield, battlefield);
-        zones.insert(Zone::Hand, Vec::new());
-        zones.insert(Zone::Library, Vec::new());
-        zones.insert(Zone::Graveyard, Vec::new());
 
-        let mut gs = GameState { zones, life: 20, turns: 0, step: GameStep::Untap };
+        let mut gs = game_with(zones, GameStep::Untap);
         gs.step();
 
-        let bf = gs.zones.get(&Zone::Battlefield).unwrap();
+        let bf = gs.zones().get(&Zone::Battlefield).unwrap();
         assert!(!crate::tappable::is_tapped(&bf[0]));
     }
+
+    #[test]
+    fn blockers_are_taken_from_defending_player()
+    {
+        let mut zones = std::collections::HashMap::new();
+        zones.insert(Zone::Battlefield, vec![grizzly_bears()]);
+
+        let mut gs = game_with(zones, GameStep::DeclareAttackers);
+        gs.players[1].zones.insert(Zone::Battlefield, vec![forest(), grizzly_bears()]);
+
+        gs.step(); // attack with the bear
+        gs.step(); // defender blocks with its bear
+        assert_eq!(gs.blocking_map.get(&1), Some(&0));
+
+        gs.step(); // trade
+        assert_eq!(gs.players[1].life, 20);
+        assert_eq!(gs.players[0].zones[&Zone::Graveyard].len(), 1);
+        assert_eq!(gs.players[1].zones[&Zone::Graveyard].len(), 1);
+        assert_eq!(gs.players[1].zones[&Zone::Battlefield].len(), 1);
+    }
+
+    #[test]
+    fn advance_stops_at_human_decision()
+    {
+        let mut zones = std::collections::HashMap::new();
+        zones.insert(Zone::Library, vec![forest(), forest()]);
+
+        let mut gs = game_with(zones, GameStep::StartTurn);
+        gs.players[1].zones.insert(Zone::Library, vec![forest(), forest()]);
+        gs.set_player_control(1, PlayerControl::Human);
+
+        // Player 0 is AI and plays its whole turn; player 1 is human and gets asked in main
+        gs.advance_until_decision();
+        assert_eq!(gs.current_player_index, 1);
+        assert_eq!(gs.step, GameStep::Main);
+        assert!(gs.waiting_for_main_decision);
+        assert_eq!(gs.deciding_player_index(), 1);
+    }
+
+    #[test]
+    fn seeded_games_are_repeatable_and_have_a_winner()
+    {
+        use rand::SeedableRng;
+        use rand::rngs::StdRng;
+
+        let decks = [Deck::example(), Deck { cards: vec![forest(); 40] }];
+        let first = crate::sim::play_game(&decks, &mut StdRng::seed_from_u64(7));
+        let second = crate::sim::play_game(&decks, &mut StdRng::seed_from_u64(7));
+
+        assert_eq!(first.turns, second.turns);
+        assert_eq!(first.players[1].zones[&Zone::Library].len(), second.players[1].zones[&Zone::Library].len());
+        // The all-land deck can never win
+        assert_eq!(first.winner(), Some(0));
+    }
+
+    #[test]
+    fn turn_walks_every_step_and_cautious_casts_after_combat()
+    {
+        let mut zones = HashMap::new();
+        zones.insert(Zone::Library, vec![forest()]);
+        zones.insert(Zone::Hand, vec![grizzly_bears()]);
+        zones.insert(Zone::Battlefield, vec![forest(), forest()]);
+        let mut gs = game_with(zones, GameStep::StartTurn);
+        gs.players[0].control = PlayerControl::Ai(crate::strategy::Strategy::Cautious);
+        gs.set_record_events(true);
+
+        while gs.step != GameStep::EndTurn
+        {
+            gs.step();
+            if gs.step == GameStep::BeginCombat
+            {
+                // The land is down but the creature waits for the second main phase
+                assert_eq!(gs.players[0].zones[&Zone::Battlefield].len(), 3);
+            }
+        }
+        assert_eq!(gs.players[0].zones[&Zone::Battlefield].len(), 4);
+
+        let steps: Vec<GameStep> = gs.drain_events().into_iter()
+            .filter_map(|e| if let GameEvent::StepChanged { step, .. } = e { Some(step) } else { None })
+            .collect();
+        assert_eq!(steps, vec![
+            GameStep::Untap, GameStep::Upkeep, GameStep::Draw, GameStep::Main, GameStep::BeginCombat,
+            GameStep::DeclareAttackers, GameStep::DeclareBlockers, GameStep::AssignDamage, GameStep::EndCombat,
+            GameStep::SecondMain, GameStep::EndStep, GameStep::Cleanup, GameStep::EndTurn,
+        ]);
+    }
 }
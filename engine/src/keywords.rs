@@ -0,0 +1,28 @@
+use crate::card::{Card, Keyword, KeywordsFragment};
+
+pub fn has_keyword(card: &Card, keyword: Keyword) -> bool
+{
+    card.get::<KeywordsFragment>().is_some_and(|kf| kf.keywords.contains(&keyword))
+}
+
+pub fn keywords(card: &Card) -> Vec<Keyword>
+{
+    card.get::<KeywordsFragment>().map(|kf| kf.keywords.clone()).unwrap_or_default()
+}
+
+pub fn add_keyword(card: &mut Card, keyword: Keyword)
+{
+    let kf = card.builtin_or_default::<KeywordsFragment>();
+    if !kf.keywords.contains(&keyword)
+    {
+        kf.keywords.push(keyword);
+    }
+}
+
+pub fn remove_keyword(card: &mut Card, keyword: Keyword)
+{
+    if let Some(kf) = card.get_mut::<KeywordsFragment>()
+    {
+        kf.keywords.retain(|k| *k != keyword);
+    }
+}
@@ -42,14 +42,47 @@ macro_rules! vlog
     }};
 }
 
+pub mod abilities;
 pub mod card;
+pub mod counters;
 pub mod creature;
+pub mod effects;
+pub mod filter;
+pub mod fragments;
 pub mod game;
+pub mod keywords;
+pub mod library;
+pub mod events;
+pub mod matchup;
+pub mod multiplayer;
+pub mod rules;
+pub mod strategy;
 pub mod tappable;
+pub mod targeting;
+pub mod token;
+pub mod triggers;
+pub mod view;
 pub mod sim;
 pub mod music;
+pub mod zones;
 
+pub use crate::abilities::*;
 pub use crate::card::*;
+pub use crate::counters::*;
 pub use crate::creature::*;
+pub use crate::effects::*;
+pub use crate::filter::*;
+pub use crate::fragments::*;
 pub use crate::game::*;
+pub use crate::keywords::*;
+pub use crate::library::*;
+pub use crate::events::*;
+pub use crate::matchup::*;
+pub use crate::multiplayer::*;
+pub use crate::rules::*;
+pub use crate::strategy::*;
 pub use crate::sim::*;
+pub use crate::targeting::*;
+pub use crate::token::*;
+pub use crate::triggers::*;
+pub use crate::view::*;
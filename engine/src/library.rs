@@ -0,0 +1,329 @@
+use rand::{RngCore, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use serde::{Serialize, Deserialize};
+
+use crate::card::{Card, CardId};
+use crate::events::GameEvent;
+use crate::filter::CardFilter;
+use crate::game::{GameState, Zone};
+use crate::rules::RulesError;
+use crate::strategy::{PlayerControl, Strategy};
+
+/// A choice about a library that a human seat makes before abilities carry on resolving.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LibraryChoice
+{
+    /// Keep some of the top `count` cards on top, in order; the rest go to the bottom, or to the
+    /// graveyard when surveilling.
+    Arrange { player: usize, count: usize, surveil: bool },
+    /// Take one of the cards found (or none) into `to`, then shuffle.
+    Search { player: usize, candidates: Vec<CardId>, to: Zone },
+}
+
+impl LibraryChoice
+{
+    pub fn player(&self) -> usize
+    {
+        match *self
+        {
+            LibraryChoice::Arrange { player, .. } | LibraryChoice::Search { player, .. } => player,
+        }
+    }
+}
+
+impl GameState
+{
+    /// A generator for the game's own shuffles. Seeded from `rng_state`, which moves on each time,
+    /// so a saved game shuffles the same way when it is loaded again.
+    pub fn game_rng(&mut self) -> StdRng
+    {
+        let mut rng = StdRng::seed_from_u64(self.rng_state);
+        self.rng_state = rng.next_u64();
+        rng
+    }
+
+    pub fn shuffle_library(&mut self, player: usize)
+    {
+        let mut rng = self.game_rng();
+        self.players[player].zones.get_mut(&Zone::Library).unwrap().shuffle(&mut rng);
+        self.emit(GameEvent::LibraryShuffled { player });
+    }
+
+    /// Up to `count` cards from the top of `player`'s library, top card first.
+    pub fn library_top(&self, player: usize, count: usize) -> Vec<&Card>
+    {
+        self.players[player].zones[&Zone::Library].iter().rev().take(count).collect()
+    }
+
+    // Take the top `count` cards off the library and put those at `top` (indices from the top, in
+    // the order given) back on it. The rest are returned, top card first.
+    fn split_top(&mut self, player: usize, count: usize, top: &[usize]) -> Result<Vec<Card>, RulesError>
+    {
+        let count = count.min(self.players[player].zones[&Zone::Library].len());
+        for (i, &index) in top.iter().enumerate()
+        {
+            if index >= count || top[..i].contains(&index)
+            {
+                return Err(RulesError::InvalidIndex(index));
+            }
+        }
+
+        let library = self.players[player].zones.get_mut(&Zone::Library).unwrap();
+        let mut looked_at: Vec<Option<Card>> = library.split_off(library.len() - count).into_iter().rev().map(Some).collect();
+        for &index in top.iter().rev()
+        {
+            library.push(looked_at[index].take().unwrap());
+        }
+        self.emit(GameEvent::LibraryArranged { player, looked_at: count, kept_on_top: top.len() });
+        Ok(looked_at.into_iter().flatten().collect())
+    }
+
+    /// Look at the top `count` cards, keep those at `top` on top in that order and put the rest on
+    /// the bottom.
+    pub fn arrange_top(&mut self, player: usize, count: usize, top: &[usize]) -> Result<(), RulesError>
+    {
+        let rest = self.split_top(player, count, top)?;
+        let library = self.players[player].zones.get_mut(&Zone::Library).unwrap();
+        for card in rest
+        {
+            library.insert(0, card);
+        }
+        Ok(())
+    }
+
+    /// As arrange_top, but the cards not kept go to the graveyard.
+    pub fn surveil_top(&mut self, player: usize, count: usize, top: &[usize]) -> Result<(), RulesError>
+    {
+        let rest = self.split_top(player, count, top)?;
+        let ids: Vec<CardId> = rest.iter().map(|card| card.id).collect();
+        self.players[player].zones.get_mut(&Zone::Library).unwrap().extend(rest);
+        for id in ids
+        {
+            self.move_card(id, Zone::Library, Zone::Graveyard)?;
+        }
+        Ok(())
+    }
+
+    // Whose judgement makes library choices for `player` when nobody is there to make them: a
+    // human's choice that is stepped past is made as Cautious would.
+    fn library_chooser(&self, player: usize) -> Strategy
+    {
+        match self.players[player].control
+        {
+            PlayerControl::Ai(strategy) => strategy,
+            PlayerControl::Human => Strategy::Cautious,
+        }
+    }
+
+    /// The cards `choice` is made from: the top of the library, or the cards a search found.
+    pub fn library_choice_cards(&self, choice: &LibraryChoice) -> Vec<&Card>
+    {
+        match choice
+        {
+            LibraryChoice::Arrange { player, count, .. } => self.library_top(*player, *count),
+            LibraryChoice::Search { player, candidates, .. } => self.players[*player].zones[&Zone::Library].iter()
+                .filter(|card| candidates.contains(&card.id))
+                .collect(),
+        }
+    }
+
+    // What the chooser's strategy would pick for `choice`
+    fn default_library_choice(&self, choice: &LibraryChoice) -> Vec<usize>
+    {
+        let player = choice.player();
+        let cards = self.library_choice_cards(choice);
+        match choice
+        {
+            LibraryChoice::Arrange { .. } => self.library_chooser(player).choose_kept_on_top(self, player, &cards),
+            LibraryChoice::Search { .. } => self.library_chooser(player).choose_search(self, player, &cards).into_iter().collect(),
+        }
+    }
+
+    // Carry out `choice` with the indices picked. Returns the new id of a card a search moved.
+    fn apply_library_choice(&mut self, choice: LibraryChoice, chosen: &[usize]) -> Result<Option<CardId>, RulesError>
+    {
+        match choice
+        {
+            LibraryChoice::Arrange { player, count, surveil: false } => self.arrange_top(player, count, chosen).map(|_| None),
+            LibraryChoice::Arrange { player, count, surveil: true } => self.surveil_top(player, count, chosen).map(|_| None),
+            LibraryChoice::Search { player, candidates, to } =>
+            {
+                if chosen.len() > 1
+                {
+                    return Err(RulesError::TooManyChosen { max: 1, chosen: chosen.len() });
+                }
+                let found = match chosen.first()
+                {
+                    Some(&index) => Some(*candidates.get(index).ok_or(RulesError::InvalidIndex(index))?),
+                    None => None,
+                };
+                let moved = found.and_then(|id| self.move_card(id, Zone::Library, to).ok().flatten());
+                self.shuffle_library(player);
+                Ok(moved)
+            }
+        }
+    }
+
+    // A human seat is asked to make `choice` (unless there is nothing to choose from); an AI seat
+    // makes it at once. Returns the new id of a card a search moved.
+    fn offer_library_choice(&mut self, choice: LibraryChoice) -> Option<CardId>
+    {
+        if self.players[choice.player()].control == PlayerControl::Human && !self.library_choice_cards(&choice).is_empty()
+        {
+            self.library_choice = Some(choice);
+            return None;
+        }
+        let chosen = self.default_library_choice(&choice);
+        self.apply_library_choice(choice, &chosen).ok().flatten()
+    }
+
+    /// Make the pending library choice, then carry on resolving. For a scry or surveil `chosen`
+    /// holds the indices (top card first) to keep on top, in order; for a search, at most one
+    /// index into the cards found.
+    pub fn choose_library(&mut self, chosen: Vec<usize>) -> Result<(), RulesError>
+    {
+        let choice = self.library_choice.clone().ok_or(RulesError::NoLibraryChoicePending)?;
+        self.apply_library_choice(choice, &chosen)?;
+        self.library_choice = None;
+        self.resolve_triggers();
+        Ok(())
+    }
+
+    /// Make a pending library choice as the chooser's strategy would.
+    pub(crate) fn make_default_library_choice(&mut self)
+    {
+        if let Some(choice) = self.library_choice.take()
+        {
+            let chosen = self.default_library_choice(&choice);
+            let _ = self.apply_library_choice(choice, &chosen);
+        }
+    }
+
+    /// Scry `count`: the player picks what stays on top.
+    pub fn scry(&mut self, player: usize, count: usize)
+    {
+        self.offer_library_choice(LibraryChoice::Arrange { player, count, surveil: false });
+    }
+
+    /// Surveil `count`: the player picks what stays on top, the rest is milled.
+    pub fn surveil(&mut self, player: usize, count: usize)
+    {
+        self.offer_library_choice(LibraryChoice::Arrange { player, count, surveil: true });
+    }
+
+    /// Search `player`'s library for a card passing `filter`, put the one they pick into `to`,
+    /// then shuffle. Returns the found card's new id; None as well while a human is choosing.
+    pub fn search_library(&mut self, player: usize, filter: &CardFilter, to: Zone) -> Option<CardId>
+    {
+        let candidates = self.players[player].zones[&Zone::Library].iter()
+            .filter(|card| self.card_matches(filter, player, card, player, Zone::Library))
+            .map(|card| card.id)
+            .collect();
+        self.offer_library_choice(LibraryChoice::Search { player, candidates, to })
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::card::{CardType, Deck, forest, grizzly_bears};
+    use crate::game::GameStep;
+
+    fn library_of(gs: &mut GameState, cards: Vec<Card>) -> Vec<CardId>
+    {
+        let ids = cards.iter().map(|card| card.id).collect();
+        gs.players[0].zones.insert(Zone::Library, cards);
+        ids
+    }
+
+    #[test]
+    fn arranging_and_surveilling_the_top_of_the_library()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        // The last card is the top one
+        let ids = library_of(&mut gs, vec![forest(), grizzly_bears(), forest(), grizzly_bears()]);
+        let top_ids = |gs: &GameState| gs.library_top(0, 4).iter().map(|card| card.id).collect::<Vec<_>>();
+
+        assert_eq!(gs.arrange_top(0, 3, &[1, 1]), Err(RulesError::InvalidIndex(1)));
+        assert_eq!(gs.arrange_top(0, 3, &[3]), Err(RulesError::InvalidIndex(3)));
+        gs.arrange_top(0, 3, &[2, 0]).unwrap();
+        assert_eq!(top_ids(&gs), vec![ids[1], ids[3], ids[0], ids[2]]);
+
+        gs.surveil_top(0, 2, &[1]).unwrap();
+        assert_eq!(gs.library_top(0, 4).len(), 3);
+        assert_eq!(gs.library_top(0, 1)[0].id, ids[3]);
+        assert_eq!(gs.players[0].zones[&Zone::Graveyard].len(), 1);
+    }
+
+    #[test]
+    fn searches_take_the_chosen_card_and_shuffle_repeatably()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        gs.players[0].zones.insert(Zone::Battlefield, vec![forest(), forest()]);
+        let mut cards: Vec<Card> = (0..10).map(|_| forest()).collect();
+        let mut big = grizzly_bears();
+        big.cost = 3;
+        cards.extend([grizzly_bears(), big]);
+        library_of(&mut gs, cards);
+        gs.rng_state = 5;
+        let mut replay = gs.clone();
+
+        // The AI wants the biggest creature it can cast next turn
+        let creature = CardFilter::Type(CardType::Creature);
+        let lotus = CardFilter::Name(String::from("Black Lotus"));
+        gs.search_library(0, &creature, Zone::Hand).unwrap();
+        assert_eq!(gs.players[0].zones[&Zone::Hand].last().unwrap().cost, 3);
+        assert_eq!(gs.players[0].zones[&Zone::Library].len(), 11);
+        assert_eq!(gs.search_library(0, &lotus, Zone::Hand), None);
+
+        replay.search_library(0, &creature, Zone::Hand);
+        replay.search_library(0, &lotus, Zone::Hand);
+        let names = |gs: &GameState| gs.library_top(0, 11).iter().map(|card| card.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&gs), names(&replay));
+    }
+
+    #[test]
+    fn human_seats_make_their_own_library_choices()
+    {
+        use crate::triggers::{add_triggered_ability, AbilityEffect, TriggerCondition, TriggeredAbility};
+
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        gs.set_player_control(0, PlayerControl::Human);
+        gs.step = GameStep::Main;
+        let ids = library_of(&mut gs, vec![forest(), grizzly_bears(), forest()]);
+        let mut seer = grizzly_bears();
+        add_triggered_ability(&mut seer, TriggeredAbility { condition: TriggerCondition::EntersBattlefield, effect: AbilityEffect::Scry(2), target: None });
+        add_triggered_ability(&mut seer, TriggeredAbility { condition: TriggerCondition::EntersBattlefield, effect: AbilityEffect::GainLife(3), target: None });
+
+        // Resolving waits on the scry, and only the chooser sees the cards
+        gs.enter_battlefield(0, seer);
+        gs.resolve_triggers();
+        assert!(gs.is_waiting_for_decision());
+        assert_eq!(gs.deciding_player_index(), 0);
+        assert_eq!(gs.pass(), Err(RulesError::LibraryChoicePending));
+        assert_eq!(gs.view_for(&[0]).looking_at.iter().map(|card| card.id).collect::<Vec<_>>(), vec![ids[2], ids[1]]);
+        assert!(gs.view_for(&[1]).looking_at.is_empty());
+
+        assert_eq!(gs.choose_library(vec![2]), Err(RulesError::InvalidIndex(2)));
+        gs.choose_library(vec![1]).unwrap();
+        assert_eq!(gs.library_top(0, 3).iter().map(|card| card.id).collect::<Vec<_>>(), vec![ids[1], ids[0], ids[2]]);
+        assert_eq!(gs.library_choice, None);
+        assert_eq!(gs.choose_library(Vec::new()), Err(RulesError::NoLibraryChoicePending));
+        assert_eq!(gs.players[0].life, 23);
+
+        // A search may find nothing, but not two cards
+        let creature = CardFilter::Type(CardType::Creature);
+        assert_eq!(gs.search_library(0, &creature, Zone::Hand), None);
+        assert_eq!(gs.choose_library(vec![0, 0]), Err(RulesError::TooManyChosen { max: 1, chosen: 2 }));
+        gs.choose_library(vec![0]).unwrap();
+        assert_eq!(gs.players[0].zones[&Zone::Hand].last().unwrap().name, "Grizzly Bears");
+
+        // Stepping past a choice makes it as the strategy would
+        gs.scry(0, 2);
+        gs.step();
+        assert_eq!(gs.library_choice, None);
+        assert_eq!(gs.step, GameStep::Main);
+    }
+}
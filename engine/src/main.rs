@@ -26,6 +26,7 @@ fn main()
     println!("  d  -> run the simulation to completion for the current deck");
     println!("  r  -> run the whole simulation to completion (all decks)");
     println!("  q  -> quit");
+    println!("  ? <filter> -> list matching cards while stepping, e.g. ? untapped creature yours");
     println!();
 
     let mut current_lands = 28;
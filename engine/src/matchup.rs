@@ -0,0 +1,175 @@
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+
+use crate::card::Deck;
+use crate::game::GameState;
+
+// z-score for the 95% confidence intervals reported with each win rate
+const CONFIDENCE_Z: f64 = 1.96;
+
+/// How the row deck did against the column deck.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MatchupCell
+{
+    pub games: u64,
+    pub wins: u64,
+    pub win_rate: f64,
+    pub ci_low: f64,
+    pub ci_high: f64,
+}
+
+/// Win rates of every deck in a gauntlet against every other. `cells[row][col]` is the row deck's
+/// record against the column deck; the diagonal (mirror matches) is left empty.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MatchupMatrix
+{
+    pub decks: Vec<String>,
+    pub cells: Vec<Vec<MatchupCell>>,
+}
+
+/// Wilson score interval for `wins` out of `games`; stays inside [0, 1] even for lopsided results.
+pub fn wilson_interval(wins: u64, games: u64) -> (f64, f64)
+{
+    if games == 0
+    {
+        return (0.0, 1.0);
+    }
+
+    let n = games as f64;
+    let p = wins as f64 / n;
+    let z2 = CONFIDENCE_Z * CONFIDENCE_Z;
+    let centre = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let spread = CONFIDENCE_Z * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt() / (1.0 + z2 / n);
+    ((centre - spread).max(0.0), (centre + spread).min(1.0))
+}
+
+impl MatchupCell
+{
+    fn new(wins: u64, games: u64) -> Self
+    {
+        let (ci_low, ci_high) = wilson_interval(wins, games);
+        MatchupCell
+        {
+            games,
+            wins,
+            win_rate: if games == 0 { 0.0 } else { wins as f64 / games as f64 },
+            ci_low,
+            ci_high,
+        }
+    }
+}
+
+impl MatchupMatrix
+{
+    /// One row per pairing: deck, opponent, games, wins, win rate and its interval.
+    pub fn to_csv(&self) -> String
+    {
+        let mut csv = String::from("deck,opponent,games,wins,win_rate,ci_low,ci_high\n");
+        for (row, deck) in self.decks.iter().enumerate()
+        {
+            for (col, opponent) in self.decks.iter().enumerate()
+            {
+                if row == col
+                {
+                    continue;
+                }
+                let cell = &self.cells[row][col];
+                csv.push_str(&format!(
+                    "{},{},{},{},{:.4},{:.4},{:.4}\n",
+                    csv_field(deck), csv_field(opponent), cell.games, cell.wins, cell.win_rate, cell.ci_low, cell.ci_high
+                ));
+            }
+        }
+        csv
+    }
+}
+
+fn csv_field(value: &str) -> String
+{
+    if value.contains([',', '"', '\n'])
+    {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    }
+    else
+    {
+        value.to_string()
+    }
+}
+
+/// Play `games` games for every pair of decks, swapping who goes first each game. `on_game` sees
+/// every finished game and returns false to stop early; pairings cut short keep what they played.
+pub fn run_matchups<R, F>(decks: &[(String, Deck)], games: u64, rng: &mut R, mut on_game: F) -> MatchupMatrix
+where
+    R: Rng + ?Sized,
+    F: FnMut(&GameState) -> bool,
+{
+    let n = decks.len();
+    let mut wins = vec![vec![0u64; n]; n];
+    let mut played = vec![vec![0u64; n]; n];
+
+    let pairings: Vec<(usize, usize)> = (0..n).flat_map(|a| ((a + 1)..n).map(move |b| (a, b))).collect();
+    'pairings: for (a, b) in pairings
+    {
+        for game_index in 0..games
+        {
+            let seats = if game_index % 2 == 0 { [a, b] } else { [b, a] };
+            let game = crate::sim::play_game(&[decks[seats[0]].1.clone(), decks[seats[1]].1.clone()], rng);
+
+            played[a][b] += 1;
+            played[b][a] += 1;
+            if let Some(winner) = game.winner()
+            {
+                let (winner, loser) = (seats[winner], seats[1 - winner]);
+                wins[winner][loser] += 1;
+            }
+
+            if !on_game(&game)
+            {
+                break 'pairings;
+            }
+        }
+    }
+
+    MatchupMatrix
+    {
+        decks: decks.iter().map(|(name, _)| name.clone()).collect(),
+        cells: (0..n).map(|row| (0..n).map(|col| MatchupCell::new(wins[row][col], played[row][col])).collect()).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::card::forest;
+
+    #[test]
+    fn gauntlet_fills_both_sides_of_each_pairing()
+    {
+        let decks = vec![
+            (String::from("Bears"), Deck::example()),
+            (String::from("Lands, only"), Deck { cards: vec![forest(); 60] }),
+        ];
+        let matrix = run_matchups(&decks, 10, &mut StdRng::seed_from_u64(1), |_| true);
+
+        assert_eq!(matrix.cells[0][1].wins, 10);
+        assert_eq!(matrix.cells[1][0].wins, 0);
+        assert_eq!(matrix.cells[1][0].games, 10);
+        assert_eq!(matrix.cells[0][0].games, 0);
+        assert!(matrix.cells[0][1].ci_low > 0.6 && matrix.cells[0][1].ci_high == 1.0);
+
+        let csv = matrix.to_csv();
+        assert_eq!(csv.lines().count(), 3);
+        assert!(csv.contains("\"Lands, only\",Bears,10,0,0.0000"));
+    }
+
+    #[test]
+    fn wilson_interval_brackets_the_rate()
+    {
+        let (low, high) = wilson_interval(50, 100);
+        assert!(low < 0.5 && high > 0.5);
+        assert!((high - 0.5 - (0.5 - low)).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,400 @@
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+
+use crate::card::Deck;
+use crate::game::{GameState, GameStep, Zone};
+use crate::events::GameEvent;
+
+// Shared life of each team in Two-Headed Giant
+pub const TWO_HEADED_GIANT_LIFE: i32 = 30;
+
+/// How a finished game ended.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameOutcome
+{
+    pub winners: Vec<usize>,
+    /// Index into `GameState::teams` of the winning team, in team games
+    #[serde(default)]
+    pub winning_team: Option<usize>,
+}
+
+/// Reasons a team layout is rejected.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TeamError
+{
+    UnknownPlayer(usize),
+    PlayerOnTwoTeams(usize),
+    PlayerWithoutTeam(usize),
+    TooFewTeams,
+}
+
+impl std::fmt::Display for TeamError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            TeamError::UnknownPlayer(p) => write!(f, "there is no player {}", p),
+            TeamError::PlayerOnTwoTeams(p) => write!(f, "player {} is on more than one team", p),
+            TeamError::PlayerWithoutTeam(p) => write!(f, "player {} is not on a team", p),
+            TeamError::TooFewTeams => write!(f, "a team game needs at least two teams"),
+        }
+    }
+}
+
+impl std::error::Error for TeamError {}
+
+impl GameState
+{
+    /// Four players in two teams of two (seats 0 and 1 against 2 and 3), each team sharing
+    /// one life total and taking its turns together.
+    pub fn two_headed_giant<R: Rng + ?Sized>(decks: &[Deck], rng: &mut R) -> Self
+    {
+        let decks: Vec<Deck> = (0..4).map(|seat| decks[seat.min(decks.len() - 1)].clone()).collect();
+        let mut gs = GameState::with_decks(&decks, rng);
+        gs.set_teams(vec![vec![0, 1], vec![2, 3]], TWO_HEADED_GIANT_LIFE)
+            .expect("two teams of two cover all four seats");
+        gs
+    }
+
+    /// Split every player into teams, in team turn order. Teammates share `life`, take their
+    /// turns back to back and win or lose together.
+    pub fn set_teams(&mut self, teams: Vec<Vec<usize>>, life: i32) -> Result<(), TeamError>
+    {
+        if teams.iter().filter(|team| !team.is_empty()).count() < 2
+        {
+            return Err(TeamError::TooFewTeams);
+        }
+
+        let mut seen = vec![false; self.players.len()];
+        for &player in teams.iter().flatten()
+        {
+            let slot = seen.get_mut(player).ok_or(TeamError::UnknownPlayer(player))?;
+            if *slot
+            {
+                return Err(TeamError::PlayerOnTwoTeams(player));
+            }
+            *slot = true;
+        }
+        if let Some(player) = seen.iter().position(|s| !s)
+        {
+            return Err(TeamError::PlayerWithoutTeam(player));
+        }
+
+        self.teams = teams.into_iter().filter(|team| !team.is_empty()).collect();
+        for player in self.players.iter_mut()
+        {
+            player.life = life;
+        }
+        self.current_player_index = self.teams[0][0];
+        Ok(())
+    }
+
+    /// Index of `player`'s team. Without teams every player is a team of their own.
+    pub fn team_of(&self, player: usize) -> usize
+    {
+        self.teams.iter().position(|team| team.contains(&player)).unwrap_or(player)
+    }
+
+    /// `player` and everyone on their team.
+    pub fn teammates(&self, player: usize) -> Vec<usize>
+    {
+        if self.teams.is_empty()
+        {
+            vec![player]
+        }
+        else
+        {
+            self.teams[self.team_of(player)].clone()
+        }
+    }
+
+    /// Take life from `player`, and from the rest of their team since teams share a life total.
+    pub fn lose_life(&mut self, player: usize, amount: i32)
+    {
+        for teammate in self.teammates(player)
+        {
+            self.players[teammate].life -= amount;
+        }
+    }
+
+    pub fn gain_life(&mut self, player: usize, amount: i32)
+    {
+        self.lose_life(player, -amount);
+    }
+
+    pub fn is_alive(&self, player: usize) -> bool
+    {
+        self.players.get(player).is_some_and(|p| !p.eliminated)
+    }
+
+    /// Players still in the game, in seat order.
+    pub fn living_players(&self) -> Vec<usize>
+    {
+        (0..self.players.len()).filter(|&p| self.is_alive(p)).collect()
+    }
+
+    /// The next player after `player` in turn order who is still in the game.
+    pub fn next_living_player(&self, player: usize) -> usize
+    {
+        let n = self.players.len();
+        (1..=n).map(|offset| (player + offset) % n)
+            .find(|&p| self.is_alive(p))
+            .unwrap_or(player)
+    }
+
+    /// Who takes the next turn after `player`. Teammates take their turns back to back as one
+    /// team turn before play passes to the next team.
+    pub fn next_turn_player(&self, player: usize) -> usize
+    {
+        if self.teams.is_empty()
+        {
+            return self.next_living_player(player);
+        }
+
+        let team = self.team_of(player);
+        let members = &self.teams[team];
+        let position = members.iter().position(|&p| p == player).unwrap_or(0);
+        if let Some(&teammate) = members[position + 1..].iter().find(|&&p| self.is_alive(p))
+        {
+            return teammate;
+        }
+
+        let n = self.teams.len();
+        (1..=n).map(|offset| &self.teams[(team + offset) % n])
+            .find_map(|team| team.iter().copied().find(|&p| self.is_alive(p)))
+            .unwrap_or(player)
+    }
+
+    /// True when `player`'s turn begins a new team turn, which is every turn without teams.
+    pub fn starts_team_turn(&self, player: usize) -> bool
+    {
+        self.teams.is_empty()
+            || self.teams[self.team_of(player)].iter().copied().find(|&p| self.is_alive(p)) == Some(player)
+    }
+
+    /// Players the current player may attack, in turn order starting after them. Teammates
+    /// are never opponents.
+    pub fn opponents(&self) -> Vec<usize>
+    {
+        self.opponents_of(self.current_player_index)
+    }
+
+    /// Living players not on `player`'s team, in turn order starting after them.
+    pub fn opponents_of(&self, player: usize) -> Vec<usize>
+    {
+        let n = self.players.len();
+        let team = self.team_of(player);
+        (1..n).map(|offset| (player + offset) % n)
+            .filter(|&p| self.is_alive(p) && self.team_of(p) != team)
+            .collect()
+    }
+
+    /// The first opponent in turn order; attacked when no other target is chosen.
+    pub fn default_defender(&self) -> usize
+    {
+        self.opponents().first().copied().unwrap_or(self.current_player_index)
+    }
+
+    /// The player an attacking creature is attacking. Attackers without a recorded target
+    /// attack the default defender.
+    pub fn attack_target(&self, attacker: usize) -> usize
+    {
+        self.attack_targets.get(&attacker).copied().unwrap_or_else(|| self.default_defender())
+    }
+
+    /// Battlefield indices of the current player's attackers that are attacking `defender`.
+    pub fn attackers_targeting(&self, defender: usize) -> Vec<usize>
+    {
+        self.attacking_creatures.iter().copied()
+            .filter(|&attacker| self.attack_target(attacker) == defender)
+            .collect()
+    }
+
+    /// Permanents on `defender`'s side of combat as (player, battlefield index): their own, then
+    /// each living teammate's, since a team blocks together. Blocker indices in a blocking map
+    /// count through this list, so without teams they are the defender's battlefield indices.
+    pub fn defending_permanents(&self, defender: usize) -> Vec<(usize, usize)>
+    {
+        let teammates = self.teammates(defender).into_iter().filter(|&p| p != defender && self.is_alive(p));
+        std::iter::once(defender).chain(teammates)
+            .flat_map(|player| (0..self.players[player].zones[&Zone::Battlefield].len()).map(move |index| (player, index)))
+            .collect()
+    }
+
+    /// The permanent that blocker index `blocker` in `defender`'s blocking map refers to.
+    pub fn blocker(&self, defender: usize, blocker: usize) -> Option<(usize, usize)>
+    {
+        self.defending_permanents(defender).get(blocker).copied()
+    }
+
+    /// Permanents already blocking for defenders other than `defender` this combat.
+    pub fn blocking_for_others(&self, defender: usize) -> Vec<(usize, usize)>
+    {
+        self.blocks.iter().filter(|(other, _)| **other != defender)
+            .flat_map(|(other, blocks)| blocks.keys().filter_map(|&blocker| self.blocker(*other, blocker)))
+            .collect()
+    }
+
+    /// Take a player out of the game. They are skipped in turn order and can't be attacked.
+    pub fn eliminate(&mut self, player: usize)
+    {
+        if self.is_alive(player)
+        {
+            self.players[player].eliminated = true;
+            self.emit(GameEvent::PlayerEliminated { player });
+        }
+    }
+
+    /// Eliminate everyone at 0 or less life, and end the game once one player (or team) is left.
+    /// A team loses together, so a player knocked out any other way takes their team with them.
+    pub fn check_eliminations(&mut self)
+    {
+        for player in self.living_players()
+        {
+            if self.players[player].life <= 0
+            {
+                self.eliminate(player);
+            }
+        }
+        for player in 0..self.players.len()
+        {
+            if !self.is_alive(player)
+            {
+                for teammate in self.teammates(player)
+                {
+                    self.eliminate(teammate);
+                }
+            }
+        }
+
+        let living = self.living_players();
+        let mut living_teams: Vec<usize> = living.iter().map(|&p| self.team_of(p)).collect();
+        living_teams.dedup();
+        if living_teams.len() <= 1
+        {
+            let winning_team = if self.teams.is_empty() { None } else { living_teams.first().copied() };
+            self.outcome = Some(GameOutcome { winners: living, winning_team });
+            self.step = GameStep::GameOver;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::card::{Deck, grizzly_bears};
+    use crate::game::Zone;
+
+    #[test]
+    fn free_for_all_attacks_one_opponent_and_skips_the_dead()
+    {
+        let mut gs = GameState::new(4, &Deck { cards: Vec::new() });
+        gs.players[0].zones.insert(Zone::Battlefield, vec![grizzly_bears(), grizzly_bears()]);
+        gs.players[2].life = 2;
+        gs.step = GameStep::DeclareAttackers;
+
+        gs.declare_attacks(vec![(0, 2), (1, 3)]).unwrap();
+        assert_eq!(gs.defenders_to_block, vec![2, 3]);
+        assert_eq!(gs.deciding_player_index(), 2);
+
+        gs.step(); // player 2 has nothing to block with
+        assert_eq!(gs.deciding_player_index(), 3);
+        gs.step();
+        gs.step(); // damage
+
+        assert_eq!(gs.players[1].life, 20);
+        assert_eq!(gs.players[3].life, 18);
+        assert!(!gs.is_alive(2));
+        assert_eq!(gs.step, GameStep::EndCombat);
+
+        while gs.step != GameStep::StartTurn
+        {
+            gs.step(); // the turn ends and passes to player 1, who can't attack the dead player 2
+        }
+        assert_eq!(gs.current_player_index, 1);
+        assert_eq!(gs.opponents(), vec![3, 0]);
+        assert_eq!(gs.next_living_player(1), 3);
+    }
+
+    #[test]
+    fn last_player_standing_wins()
+    {
+        let mut gs = GameState::new(3, &Deck { cards: Vec::new() });
+        gs.players[1].life = 0;
+        gs.check_eliminations();
+        assert!(!gs.is_game_over());
+
+        gs.players[2].life = -3;
+        gs.check_eliminations();
+        assert!(gs.is_game_over());
+        assert_eq!(gs.outcome, Some(GameOutcome { winners: vec![0], winning_team: None }));
+        assert_eq!(gs.winner(), Some(0));
+    }
+
+    #[test]
+    fn two_headed_giant_teams_share_life_and_turns()
+    {
+        use rand::SeedableRng;
+        let mut gs = GameState::two_headed_giant(&[Deck::example()], &mut rand::rngs::StdRng::seed_from_u64(3));
+        assert_eq!(gs.players.len(), 4);
+        assert!(gs.players.iter().all(|p| p.life == TWO_HEADED_GIANT_LIFE));
+
+        // Team turns: 0 then 1 in the same turn, then 2 and 3
+        assert_eq!(gs.next_turn_player(0), 1);
+        assert_eq!(gs.next_turn_player(1), 2);
+        assert!(gs.starts_team_turn(0) && !gs.starts_team_turn(1));
+        assert_eq!(gs.opponents(), vec![2, 3]);
+
+        gs.lose_life(3, 7);
+        assert_eq!((gs.players[2].life, gs.players[3].life), (23, 23));
+
+        // Decking out one head loses the game for both
+        gs.eliminate(2);
+        gs.check_eliminations();
+        assert!(gs.is_game_over());
+        assert_eq!(gs.outcome, Some(GameOutcome { winners: vec![0, 1], winning_team: Some(0) }));
+    }
+
+    #[test]
+    fn two_headed_giant_teams_block_together()
+    {
+        use crate::card::{forest, grizzly_bears};
+        use crate::rules::RulesError;
+        use std::collections::HashMap;
+
+        let mut gs = GameState::new(4, &Deck { cards: Vec::new() });
+        gs.set_teams(vec![vec![0, 1], vec![2, 3]], TWO_HEADED_GIANT_LIFE).unwrap();
+        gs.players[0].zones.insert(Zone::Battlefield, vec![grizzly_bears(), grizzly_bears()]);
+        gs.players[2].zones.insert(Zone::Battlefield, vec![forest()]);
+        gs.players[3].zones.insert(Zone::Battlefield, vec![grizzly_bears()]);
+        gs.step = GameStep::DeclareAttackers;
+        gs.declare_attacks(vec![(0, 2), (1, 3)]).unwrap();
+
+        // Player 2 has no creature of their own, but their teammate's bear comes after their forest
+        assert_eq!(gs.defending_permanents(2), vec![(2, 0), (3, 0)]);
+        gs.step();
+        assert_eq!(gs.blocks[&2], HashMap::from([(1, 0)]));
+
+        // The bear can't block for player 3 as well
+        assert_eq!(gs.defending_permanents(3), vec![(3, 0), (2, 0)]);
+        assert_eq!(gs.validate_blockers(&HashMap::from([(0, 1)])), Err(RulesError::AlreadyBlocking(0)));
+        gs.declare_blockers(HashMap::new()).unwrap();
+
+        gs.step();
+        assert!(gs.players[3].zones[&Zone::Battlefield].is_empty());
+        assert_eq!(gs.players[0].zones[&Zone::Battlefield].len(), 1);
+        assert_eq!(gs.players[2].life, TWO_HEADED_GIANT_LIFE - 2);
+    }
+
+    #[test]
+    fn teams_must_cover_every_player_once()
+    {
+        let mut gs = GameState::new(4, &Deck { cards: Vec::new() });
+        assert_eq!(gs.set_teams(vec![vec![0, 1, 2, 3]], 30), Err(TeamError::TooFewTeams));
+        assert_eq!(gs.set_teams(vec![vec![0, 1], vec![1, 2, 3]], 30), Err(TeamError::PlayerOnTwoTeams(1)));
+        assert_eq!(gs.set_teams(vec![vec![0, 1], vec![2]], 30), Err(TeamError::PlayerWithoutTeam(3)));
+        assert_eq!(gs.set_teams(vec![vec![0, 1], vec![2, 7]], 30), Err(TeamError::UnknownPlayer(7)));
+    }
+}
@@ -0,0 +1,534 @@
+use std::collections::{HashMap, HashSet};
+use serde::{Serialize, Deserialize};
+
+use crate::game::{GameState, GameStep, Zone};
+use crate::card::{CardId, CardType};
+use crate::events::GameEvent;
+use crate::targeting::Target;
+use crate::ELoggingVerbosity;
+
+/// Reasons a manual declaration can be rejected by the engine.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RulesError
+{
+    WrongStep { expected: GameStep, actual: GameStep },
+    InvalidIndex(usize),
+    NotACreature(usize),
+    Tapped(usize),
+    SummoningSick(usize),
+    DuplicateAttacker(usize),
+    NotAttacking(usize),
+    AttackerAlreadyBlocked(usize),
+    AlreadyBlocking(usize),
+    NotALand(usize),
+    LandAlreadyPlayed,
+    InsufficientMana { needed: u32, available: u32 },
+    InvalidTarget(usize),
+    TargetsPending,
+    NoTargetsPending,
+    LibraryChoicePending,
+    NoLibraryChoicePending,
+    TooManyChosen { max: usize, chosen: usize },
+    WrongTargetCount { min: usize, max: usize, chosen: usize },
+    IllegalTarget(Target),
+    NoSuchAbility(usize),
+    NotEnoughLife { needed: i32, life: i32 },
+    WrongDiscardCount { excess: usize, chosen: usize },
+    CardNotInZone { card: CardId, zone: Zone },
+}
+
+impl std::fmt::Display for RulesError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            RulesError::WrongStep { expected, actual } => write!(f, "expected step {:?} but game is in {:?}", expected, actual),
+            RulesError::InvalidIndex(i) => write!(f, "no card at index {}", i),
+            RulesError::NotACreature(i) => write!(f, "card at index {} is not a creature", i),
+            RulesError::Tapped(i) => write!(f, "creature at index {} is tapped", i),
+            RulesError::SummoningSick(i) => write!(f, "creature at index {} has summoning sickness", i),
+            RulesError::DuplicateAttacker(i) => write!(f, "creature at index {} was declared as an attacker more than once", i),
+            RulesError::NotAttacking(i) => write!(f, "creature at index {} is not attacking", i),
+            RulesError::AttackerAlreadyBlocked(i) => write!(f, "attacker at index {} is already blocked", i),
+            RulesError::AlreadyBlocking(i) => write!(f, "blocker at index {} is already blocking for a teammate", i),
+            RulesError::NotALand(i) => write!(f, "card at hand index {} is not a land", i),
+            RulesError::LandAlreadyPlayed => write!(f, "a land has already been played this turn"),
+            RulesError::InsufficientMana { needed, available } => write!(f, "needs {} mana but only {} is available", needed, available),
+            RulesError::InvalidTarget(p) => write!(f, "player {} can't be attacked", p),
+            RulesError::TargetsPending => write!(f, "targets must be chosen first"),
+            RulesError::NoTargetsPending => write!(f, "no ability is waiting for targets"),
+            RulesError::LibraryChoicePending => write!(f, "the library choice must be made first"),
+            RulesError::NoLibraryChoicePending => write!(f, "no library choice is waiting"),
+            RulesError::TooManyChosen { max, chosen } => write!(f, "at most {} may be chosen but {} were", max, chosen),
+            RulesError::WrongTargetCount { min, max, chosen } => write!(f, "needs {} to {} targets but {} were chosen", min, max, chosen),
+            RulesError::IllegalTarget(t) => write!(f, "{:?} can't be targeted", t),
+            RulesError::NoSuchAbility(i) => write!(f, "no activated ability at index {}", i),
+            RulesError::NotEnoughLife { needed, life } => write!(f, "needs {} life but only has {}", needed, life),
+            RulesError::WrongDiscardCount { excess, chosen } => write!(f, "{} cards chosen to discard but only {} are over the hand size", chosen, excess),
+            RulesError::CardNotInZone { card, zone } => write!(f, "card {} is not in a {:?}", card, zone),
+        }
+    }
+}
+
+impl std::error::Error for RulesError {}
+
+impl GameState
+{
+    // Lands and creatures can be played in either main phase
+    pub(crate) fn expect_main_phase(&self) -> Result<(), RulesError>
+    {
+        self.expect_step(if self.step.is_main_phase() { self.step } else { GameStep::Main })
+    }
+
+    // Nothing else happens while an ability waits on a human's choice
+    pub(crate) fn expect_no_pending_choice(&self) -> Result<(), RulesError>
+    {
+        if self.waiting_for_targets
+        {
+            return Err(RulesError::TargetsPending);
+        }
+        if self.library_choice.is_some()
+        {
+            return Err(RulesError::LibraryChoicePending);
+        }
+        Ok(())
+    }
+
+    fn expect_step(&self, expected: GameStep) -> Result<(), RulesError>
+    {
+        self.expect_no_pending_choice()?;
+        if self.step != expected
+        {
+            return Err(RulesError::WrongStep { expected, actual: self.step });
+        }
+        Ok(())
+    }
+
+    /// Check that the current player may attack with the given battlefield indices.
+    pub fn validate_attackers(&self, attacking_indices: &[usize]) -> Result<(), RulesError>
+    {
+        self.expect_step(GameStep::DeclareAttackers)?;
+
+        let battlefield = self.zones().get(&Zone::Battlefield).unwrap();
+        let mut seen = HashSet::new();
+        for &idx in attacking_indices
+        {
+            let card = battlefield.get(idx).ok_or(RulesError::InvalidIndex(idx))?;
+            if !seen.insert(idx)
+            {
+                return Err(RulesError::DuplicateAttacker(idx));
+            }
+            if !self.has_effective_type(card, CardType::Creature)
+            {
+                return Err(RulesError::NotACreature(idx));
+            }
+            if crate::tappable::is_tapped(card)
+            {
+                return Err(RulesError::Tapped(idx));
+            }
+            if crate::creature::has_summoning_sickness(card)
+            {
+                return Err(RulesError::SummoningSick(idx));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check a blocker -> attacker map. Blocker indices refer to the defending player's battlefield,
+    /// attacker indices to the current player's battlefield.
+    pub fn validate_blockers(&self, blocking_map: &HashMap<usize, usize>) -> Result<(), RulesError>
+    {
+        self.expect_step(GameStep::DeclareBlockers)?;
+
+        let defender = self.defending_player_index();
+        let permanents = self.defending_permanents(defender);
+        let busy = self.blocking_for_others(defender);
+        let attackers = self.attackers_targeting(defender);
+        let mut blocked_attackers = HashSet::new();
+
+        // Sort so the reported error doesn't depend on HashMap iteration order
+        let mut blocks: Vec<(usize, usize)> = blocking_map.iter().map(|(b, a)| (*b, *a)).collect();
+        blocks.sort();
+
+        for (blocker_idx, attacker_idx) in blocks
+        {
+            let &(player, index) = permanents.get(blocker_idx).ok_or(RulesError::InvalidIndex(blocker_idx))?;
+            let blocker = &self.players[player].zones[&Zone::Battlefield][index];
+            if busy.contains(&(player, index))
+            {
+                return Err(RulesError::AlreadyBlocking(blocker_idx));
+            }
+            if !self.has_effective_type(blocker, CardType::Creature)
+            {
+                return Err(RulesError::NotACreature(blocker_idx));
+            }
+            if crate::tappable::is_tapped(blocker)
+            {
+                return Err(RulesError::Tapped(blocker_idx));
+            }
+            // Defenders can only block creatures attacking them
+            if !attackers.contains(&attacker_idx)
+            {
+                return Err(RulesError::NotAttacking(attacker_idx));
+            }
+            if !blocked_attackers.insert(attacker_idx)
+            {
+                return Err(RulesError::AttackerAlreadyBlocked(attacker_idx));
+            }
+        }
+        Ok(())
+    }
+
+    /// Declare attackers against the next player in turn order.
+    pub fn declare_attackers(&mut self, attacking_indices: Vec<usize>) -> Result<(), RulesError>
+    {
+        let defender = self.defending_player_index();
+        self.declare_attacks(attacking_indices.into_iter().map(|attacker| (attacker, defender)).collect())
+    }
+
+    /// Declare (attacker battlefield index, attacked player) pairs for the current player, tapping
+    /// the attackers and advancing to blocks.
+    pub fn declare_attacks(&mut self, attacks: Vec<(usize, usize)>) -> Result<(), RulesError>
+    {
+        let attacking_indices: Vec<usize> = attacks.iter().map(|(attacker, _)| *attacker).collect();
+        self.validate_attackers(&attacking_indices)?;
+
+        let opponents = self.opponents();
+        if let Some(&(_, target)) = attacks.iter().find(|(_, target)| !opponents.contains(target))
+        {
+            return Err(RulesError::InvalidTarget(target));
+        }
+
+        {
+            let battlefield = self.zones_mut().get_mut(&Zone::Battlefield).unwrap();
+            for &idx in &attacking_indices
+            {
+                crate::tappable::set_tapped(&mut battlefield[idx], true);
+            }
+        }
+
+        let targets: HashMap<usize, usize> = attacks.into_iter().collect();
+        self.emit(GameEvent::AttackersDeclared { player: self.current_player_index, attackers: attacking_indices.clone(), targets: targets.clone() });
+
+        // Every attacked player gets to block, in turn order
+        self.defenders_to_block = opponents.into_iter().filter(|p| targets.values().any(|t| t == p)).collect();
+        self.attack_targets = targets;
+        self.attacking_creatures = attacking_indices;
+        self.blocking_map.clear();
+        self.blocks.clear();
+        self.waiting_for_attack_decision = false;
+        self.step = GameStep::DeclareBlockers;
+        Ok(())
+    }
+
+    /// Declare blockers for the defending player. Moves on to the next attacked player, or to
+    /// damage once everyone attacked has blocked.
+    pub fn declare_blockers(&mut self, blocking_map: HashMap<usize, usize>) -> Result<(), RulesError>
+    {
+        self.validate_blockers(&blocking_map)?;
+
+        let defender = self.defending_player_index();
+        self.emit(GameEvent::BlockersDeclared { player: defender, blocks: blocking_map.clone() });
+        self.blocks.insert(defender, blocking_map.clone());
+        self.blocking_map = blocking_map;
+        self.waiting_for_block_decision = false;
+
+        if !self.defenders_to_block.is_empty()
+        {
+            self.defenders_to_block.remove(0);
+        }
+        if self.defenders_to_block.is_empty()
+        {
+            self.step = GameStep::AssignDamage;
+        }
+        Ok(())
+    }
+}
+
+impl GameState
+{
+    /// Play the land at `hand_index` for the current player. One land per turn.
+    pub fn play_land(&mut self, hand_index: usize) -> Result<(), RulesError>
+    {
+        self.expect_main_phase()?;
+
+        let card = self.zones().get(&Zone::Hand).unwrap().get(hand_index).ok_or(RulesError::InvalidIndex(hand_index))?;
+        if !card.is_type(CardType::Land)
+        {
+            return Err(RulesError::NotALand(hand_index));
+        }
+        if self.lands_played_this_turn > 0
+        {
+            return Err(RulesError::LandAlreadyPlayed);
+        }
+
+        let card = self.zones_mut().get_mut(&Zone::Hand).unwrap().remove(hand_index);
+        vlog!(ELoggingVerbosity::Verbose, "Play {}", card.name);
+        self.emit(GameEvent::LandPlayed { player: self.current_player_index, card: card.name.clone() });
+        self.enter_battlefield(self.current_player_index, card);
+        self.lands_played_this_turn += 1;
+        self.resolve_triggers();
+        Ok(())
+    }
+
+    /// Cast the creature at `hand_index`, paying its cost from the mana pool, then lands and mana sources.
+    pub fn cast_creature(&mut self, hand_index: usize) -> Result<(), RulesError>
+    {
+        self.expect_main_phase()?;
+
+        let card = self.zones().get(&Zone::Hand).unwrap().get(hand_index).ok_or(RulesError::InvalidIndex(hand_index))?;
+        if !crate::creature::is_creature(card)
+        {
+            return Err(RulesError::NotACreature(hand_index));
+        }
+        let available = self.available_mana();
+        if card.cost > available
+        {
+            return Err(RulesError::InsufficientMana { needed: card.cost, available });
+        }
+
+        // Spend floating mana first, then tap lands and mana abilities for the rest. The card only
+        // leaves the hand once that has worked.
+        self.pay_mana(self.current_player_index, card.cost, None)?;
+
+        let mut card = self.zones_mut().get_mut(&Zone::Hand).unwrap().remove(hand_index);
+        vlog!(ELoggingVerbosity::Verbose, "Cast {}", card.name);
+        self.emit(GameEvent::CreatureCast { player: self.current_player_index, card: card.name.clone() });
+
+        // Newly cast creatures have summoning sickness
+        crate::creature::set_summoning_sickness(&mut card, true);
+
+        self.enter_battlefield(self.current_player_index, card);
+        self.resolve_triggers();
+        Ok(())
+    }
+
+    /// Pass on the pending decision: end a main phase, or declare no attackers / no blockers.
+    pub fn pass(&mut self) -> Result<(), RulesError>
+    {
+        self.expect_no_pending_choice()?;
+        let before = self.step;
+        match self.step
+        {
+            GameStep::Main | GameStep::SecondMain =>
+            {
+                self.waiting_for_main_decision = false;
+                self.step = if self.step == GameStep::Main { GameStep::BeginCombat } else { GameStep::EndStep };
+            }
+            GameStep::DeclareAttackers => self.declare_attackers(Vec::new())?,
+            GameStep::DeclareBlockers => self.declare_blockers(HashMap::new())?,
+            actual => return Err(RulesError::WrongStep { expected: GameStep::Main, actual }),
+        }
+        // The same as when step() moves on, so a human passing sees the triggers an AI would
+        self.finish_step(before);
+        Ok(())
+    }
+
+    /// Discard the cards at `hand_indices` in cleanup, at most as many as the current player holds
+    /// over the maximum hand size. Cleanup carries on once the hand is small enough.
+    pub fn discard_to_hand_size(&mut self, hand_indices: Vec<usize>) -> Result<(), RulesError>
+    {
+        self.expect_step(GameStep::Cleanup)?;
+
+        let player = self.current_player_index;
+        let excess = self.excess_hand_size(player);
+        if hand_indices.is_empty() || hand_indices.len() > excess
+        {
+            return Err(RulesError::WrongDiscardCount { excess, chosen: hand_indices.len() });
+        }
+        let hand = self.zones().get(&Zone::Hand).unwrap();
+        let mut seen = HashSet::new();
+        if let Some(&idx) = hand_indices.iter().find(|&&idx| idx >= hand.len() || !seen.insert(idx))
+        {
+            return Err(RulesError::InvalidIndex(idx));
+        }
+
+        self.discard_cards(player, hand_indices);
+        if self.excess_hand_size(player) == 0
+        {
+            self.waiting_for_discard = false;
+        }
+        Ok(())
+    }
+
+    /// Choose targets for the triggered ability waiting on them, then carry on resolving.
+    pub fn choose_targets(&mut self, targets: Vec<Target>) -> Result<(), RulesError>
+    {
+        let item = self.pending_triggers.first().filter(|_| self.waiting_for_targets).ok_or(RulesError::NoTargetsPending)?;
+        self.check_targets(item, &targets)?;
+
+        self.pending_triggers[0].targets = Some(targets);
+        self.waiting_for_targets = false;
+        self.resolve_triggers();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::card::{Deck, forest, grizzly_bears};
+
+    fn game_at(step: GameStep, attacker_bf: Vec<crate::card::Card>, defender_bf: Vec<crate::card::Card>) -> GameState
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        gs.players[0].zones.insert(Zone::Battlefield, attacker_bf);
+        gs.players[1].zones.insert(Zone::Battlefield, defender_bf);
+        gs.step = step;
+        gs
+    }
+
+    #[test]
+    fn attackers_must_be_ready_creatures()
+    {
+        let mut sick = grizzly_bears();
+        crate::creature::set_summoning_sickness(&mut sick, true);
+        let mut tapped = grizzly_bears();
+        crate::tappable::set_tapped(&mut tapped, true);
+
+        let gs = game_at(GameStep::DeclareAttackers, vec![forest(), sick, tapped, grizzly_bears()], Vec::new());
+
+        assert_eq!(gs.validate_attackers(&[0]), Err(RulesError::NotACreature(0)));
+        assert_eq!(gs.validate_attackers(&[1]), Err(RulesError::SummoningSick(1)));
+        assert_eq!(gs.validate_attackers(&[2]), Err(RulesError::Tapped(2)));
+        assert_eq!(gs.validate_attackers(&[9]), Err(RulesError::InvalidIndex(9)));
+        assert_eq!(gs.validate_attackers(&[3, 3]), Err(RulesError::DuplicateAttacker(3)));
+        assert_eq!(gs.validate_attackers(&[3]), Ok(()));
+    }
+
+    #[test]
+    fn rejected_attack_leaves_state_untouched()
+    {
+        let mut gs = game_at(GameStep::DeclareAttackers, vec![grizzly_bears(), forest()], Vec::new());
+
+        assert!(gs.declare_attackers(vec![0, 1]).is_err());
+        assert_eq!(gs.step, GameStep::DeclareAttackers);
+        assert!(!crate::tappable::is_tapped(&gs.zones()[&Zone::Battlefield][0]));
+
+        gs.declare_attackers(vec![0]).unwrap();
+        assert_eq!(gs.step, GameStep::DeclareBlockers);
+        assert!(crate::tappable::is_tapped(&gs.zones()[&Zone::Battlefield][0]));
+    }
+
+    #[test]
+    fn blockers_come_from_defender_and_block_attackers()
+    {
+        let mut gs = game_at(GameStep::DeclareBlockers, vec![grizzly_bears(), grizzly_bears()], vec![grizzly_bears(), grizzly_bears(), forest()]);
+        gs.attacking_creatures = vec![0];
+
+        assert_eq!(gs.validate_blockers(&HashMap::from([(2, 0)])), Err(RulesError::NotACreature(2)));
+        assert_eq!(gs.validate_blockers(&HashMap::from([(0, 1)])), Err(RulesError::NotAttacking(1)));
+        assert_eq!(gs.validate_blockers(&HashMap::from([(0, 0), (1, 0)])), Err(RulesError::AttackerAlreadyBlocked(0)));
+        assert_eq!(gs.validate_blockers(&HashMap::from([(5, 0)])), Err(RulesError::InvalidIndex(5)));
+
+        gs.declare_blockers(HashMap::from([(1, 0)])).unwrap();
+        assert_eq!(gs.step, GameStep::AssignDamage);
+    }
+
+    #[test]
+    fn attacks_must_target_a_living_opponent()
+    {
+        let mut gs = GameState::new(3, &Deck { cards: Vec::new() });
+        gs.players[0].zones.insert(Zone::Battlefield, vec![grizzly_bears()]);
+        gs.step = GameStep::DeclareAttackers;
+        gs.eliminate(2);
+
+        assert_eq!(gs.declare_attacks(vec![(0, 0)]), Err(RulesError::InvalidTarget(0)));
+        assert_eq!(gs.declare_attacks(vec![(0, 2)]), Err(RulesError::InvalidTarget(2)));
+        gs.declare_attacks(vec![(0, 1)]).unwrap();
+        assert_eq!(gs.attack_target(0), 1);
+    }
+
+    #[test]
+    fn declarations_rejected_outside_their_step()
+    {
+        let mut gs = game_at(GameStep::Main, vec![grizzly_bears()], Vec::new());
+        assert_eq!(
+            gs.declare_attackers(vec![0]),
+            Err(RulesError::WrongStep { expected: GameStep::DeclareAttackers, actual: GameStep::Main })
+        );
+    }
+
+    #[test]
+    fn manual_main_phase_plays_one_land_and_pays_for_creatures()
+    {
+        let mut gs = game_at(GameStep::Main, vec![forest()], Vec::new());
+        gs.players[0].zones.insert(Zone::Hand, vec![forest(), grizzly_bears(), forest()]);
+
+        assert_eq!(gs.play_land(1), Err(RulesError::NotALand(1)));
+        assert_eq!(gs.cast_creature(1), Err(RulesError::InsufficientMana { needed: 2, available: 1 }));
+
+        gs.play_land(0).unwrap();
+        assert_eq!(gs.play_land(1), Err(RulesError::LandAlreadyPlayed));
+
+        gs.cast_creature(0).unwrap();
+        let bf = &gs.zones()[&Zone::Battlefield];
+        assert_eq!(bf.len(), 3);
+        assert!(crate::creature::has_summoning_sickness(&bf[2]));
+        assert_eq!(gs.available_mana(), 0);
+    }
+
+    #[test]
+    fn pass_clears_main_decision_and_advances()
+    {
+        let mut gs = game_at(GameStep::Main, Vec::new(), Vec::new());
+        gs.players[0].control = crate::strategy::PlayerControl::Human;
+        gs.step();
+        assert!(gs.waiting_for_main_decision);
+        assert_eq!(gs.step, GameStep::Main);
+
+        gs.pass().unwrap();
+        assert!(!gs.waiting_for_main_decision);
+        assert_eq!(gs.step, GameStep::BeginCombat);
+
+        gs.step();
+        gs.step();
+        assert!(gs.waiting_for_attack_decision);
+        gs.pass().unwrap();
+        assert!(!gs.waiting_for_attack_decision);
+        assert_eq!(gs.step, GameStep::DeclareBlockers);
+    }
+
+    #[test]
+    fn passing_into_the_end_step_triggers_its_abilities()
+    {
+        use crate::triggers::{AbilityEffect, TriggerCondition, TriggeredAbility, add_triggered_ability};
+        let mut bears = grizzly_bears();
+        add_triggered_ability(&mut bears, TriggeredAbility { condition: TriggerCondition::BeginningOfEndStep, effect: AbilityEffect::GainLife(3), target: None });
+        let mut gs = game_at(GameStep::SecondMain, vec![bears], Vec::new());
+        gs.players[0].control = crate::strategy::PlayerControl::Human;
+        gs.players[0].mana_pool = 2;
+        gs.set_record_events(true);
+
+        gs.step();
+        assert!(gs.waiting_for_main_decision);
+        gs.pass().unwrap();
+        assert_eq!(gs.step, GameStep::EndStep);
+        assert_eq!(gs.players[0].life, 23);
+        assert_eq!(gs.players[0].mana_pool, 0);
+        assert!(gs.drain_events().contains(&GameEvent::StepChanged { player: 0, step: GameStep::EndStep }));
+    }
+
+    #[test]
+    fn human_discards_down_to_hand_size_in_cleanup()
+    {
+        let mut gs = game_at(GameStep::Cleanup, Vec::new(), Vec::new());
+        gs.players[0].control = crate::strategy::PlayerControl::Human;
+        gs.players[0].zones.insert(Zone::Hand, vec![forest(); 9]);
+        gs.step();
+        assert!(gs.waiting_for_discard && gs.is_waiting_for_decision());
+
+        assert_eq!(gs.discard_to_hand_size(vec![0, 1, 2]), Err(RulesError::WrongDiscardCount { excess: 2, chosen: 3 }));
+        assert_eq!(gs.discard_to_hand_size(vec![4, 4]), Err(RulesError::InvalidIndex(4)));
+        gs.discard_to_hand_size(vec![8]).unwrap();
+        assert!(gs.waiting_for_discard);
+        gs.discard_to_hand_size(vec![0]).unwrap();
+        assert!(!gs.waiting_for_discard);
+
+        gs.step();
+        assert_eq!(gs.step, GameStep::EndTurn);
+        assert_eq!(gs.players[0].zones[&Zone::Graveyard].len(), 2);
+    }
+}
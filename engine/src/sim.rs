@@ -1,7 +1,9 @@
 use std::io::{self, Write};
+use rand::Rng;
 
 use crate::game::{GameState, ProgramState, StepCommand, GameStep};
 use crate::card::Deck;
+use crate::filter::CardFilter;
 
 pub fn parse_command(input: &str) -> StepCommand
 {
@@ -17,15 +19,42 @@ pub fn parse_command(input: &str) -> StepCommand
     }
 }
 
-fn wait_for_command() -> StepCommand
+/// Print the cards in `game` matching a filter query such as `untapped creature yours`, as
+/// player 0 sees it.
+pub fn print_query(game: &GameState, query: &str)
 {
-    print!("> ");
-    io::stdout().flush().unwrap();
+    match query.parse::<CardFilter>()
+    {
+        Ok(filter) =>
+        {
+            let cards = game.cards_matching(&filter, 0);
+            for (player, zone, card) in &cards
+            {
+                println!("  Player {} {:?}: {}", player + 1, zone, card.name);
+            }
+            println!("{} cards match {}", cards.len(), filter);
+        }
+        Err(e) => println!("{}", e),
+    }
+}
 
-    let mut input = String::new();
-    io::stdin().read_line(&mut input).unwrap();
+fn wait_for_command(game: &GameState) -> StepCommand
+{
+    loop
+    {
+        print!("> ");
+        io::stdout().flush().unwrap();
 
-    parse_command(input.trim())
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).unwrap();
+
+        // "? <filter>" queries the game and asks again
+        match input.trim().strip_prefix('?')
+        {
+            Some(query) => print_query(game, query),
+            None => return parse_command(input.trim()),
+        }
+    }
 }
 
 pub fn simulate_game(deck: &Deck, step_mode: StepCommand) -> (u32, StepCommand)
@@ -48,7 +77,7 @@ pub fn simulate_game(deck: &Deck, step_mode: StepCommand) -> (u32, StepCommand)
                 game.describe(true);
 
                 // get new command
-                mode = wait_for_command();
+                mode = wait_for_command(&game);
             }
 
             StepCommand::StepTurn =>
@@ -69,7 +98,7 @@ pub fn simulate_game(deck: &Deck, step_mode: StepCommand) -> (u32, StepCommand)
                 }
 
                 game.describe(true);
-                mode = wait_for_command();
+                mode = wait_for_command(&game);
             }
 
             StepCommand::RunGame | StepCommand::RunDeck | StepCommand::RunAll =>
@@ -85,7 +114,7 @@ pub fn simulate_game(deck: &Deck, step_mode: StepCommand) -> (u32, StepCommand)
                     println!("Game over in {} turns.", game.turns);
 
                     // get next command
-                    mode = wait_for_command();
+                    mode = wait_for_command(&game);
                 }
 
                 // exit after running to completion
@@ -99,7 +128,7 @@ pub fn simulate_game(deck: &Deck, step_mode: StepCommand) -> (u32, StepCommand)
 
             StepCommand::Invalid =>
             {
-                mode = wait_for_command();
+                mode = wait_for_command(&game);
             }
         }
     }
@@ -107,6 +136,17 @@ pub fn simulate_game(deck: &Deck, step_mode: StepCommand) -> (u32, StepCommand)
     (game.turns, mode)
 }
 
+/// Play one game between `decks` (in seat order) to the end without stopping for input.
+pub fn play_game<R: Rng + ?Sized>(decks: &[Deck], rng: &mut R) -> GameState
+{
+    let mut game = GameState::with_decks(decks, rng);
+    while !game.is_game_over()
+    {
+        game.step();
+    }
+    game
+}
+
 pub fn try_scenario(lands: u32, nonlands: u32, program_state: &mut ProgramState) -> f64
 {
     let mut cards = Vec::new();
@@ -0,0 +1,287 @@
+use std::collections::{HashMap, HashSet};
+use serde::{Serialize, Deserialize};
+
+use crate::card::{Card, CardType};
+use crate::game::{GameState, Zone};
+use crate::filter::CardFilter;
+use crate::targeting::{Target, TargetController};
+use crate::triggers::StackItem;
+
+/// How an AI seat makes its decisions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Strategy
+{
+    /// Attack with everything that can, block only when the blocker kills the attacker.
+    Aggressive,
+    /// Only make attacks no untapped defender can punish, block whenever the blocker survives or trades.
+    Cautious,
+}
+
+/// Who drives a seat: the browser/CLI user or an AI strategy.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlayerControl
+{
+    Human,
+    Ai(Strategy),
+}
+
+impl Default for PlayerControl
+{
+    fn default() -> Self
+    {
+        PlayerControl::Ai(Strategy::Aggressive)
+    }
+}
+
+fn power(gs: &GameState, card: &crate::card::Card) -> i32
+{
+    gs.effective_stats(card).map(|s| s.power as i32).unwrap_or(0)
+}
+
+fn toughness(gs: &GameState, card: &crate::card::Card) -> i32
+{
+    gs.effective_stats(card).map(|s| s.toughness as i32).unwrap_or(0)
+}
+
+fn can_block(gs: &GameState, card: &crate::card::Card) -> bool
+{
+    gs.has_effective_type(card, CardType::Creature) && !crate::tappable::is_tapped(card)
+}
+
+impl Strategy
+{
+    /// Hand index of the land to play this turn, if any.
+    pub fn choose_land(&self, gs: &GameState) -> Option<usize>
+    {
+        gs.zones().get(&Zone::Hand).unwrap().iter().position(|c| c.is_type(CardType::Land))
+    }
+
+    /// Cautious keeps its creatures in hand until the main phase after combat, so they give away
+    /// nothing before attacks are made.
+    pub fn casts_after_combat(&self) -> bool
+    {
+        *self == Strategy::Cautious
+    }
+
+    /// Hand index of the next creature to cast with the mana that is left, if any.
+    pub fn choose_cast(&self, gs: &GameState) -> Option<usize>
+    {
+        let available_mana = gs.available_mana();
+        gs.zones().get(&Zone::Hand).unwrap().iter()
+            .position(|card| crate::creature::is_creature(card) && card.cost <= available_mana)
+    }
+
+    /// Opponent to send this turn's attack at: Aggressive goes after the lowest life total,
+    /// Cautious after the opponent with the fewest untapped blockers.
+    pub fn choose_attack_target(&self, gs: &GameState) -> Option<usize>
+    {
+        let opponents = gs.opponents();
+        match self
+        {
+            Strategy::Aggressive => opponents.into_iter().min_by_key(|&p| gs.players[p].life),
+            Strategy::Cautious => opponents.into_iter().min_by_key(|&p|
+            {
+                let blockers = gs.players[p].zones.get(&Zone::Battlefield).unwrap().iter().filter(|c| can_block(gs, c)).count();
+                (blockers, gs.players[p].life)
+            }),
+        }
+    }
+
+    /// (battlefield index, attacked player) for each of the current player's creatures to attack with.
+    pub fn choose_attacks(&self, gs: &GameState) -> Vec<(usize, usize)>
+    {
+        let Some(target) = self.choose_attack_target(gs) else { return Vec::new() };
+        let battlefield = gs.zones().get(&Zone::Battlefield).unwrap();
+        let defender_battlefield = gs.players[target].zones.get(&Zone::Battlefield).unwrap();
+
+        battlefield.iter().enumerate()
+            .filter(|(_, card)| gs.has_effective_type(card, CardType::Creature)
+                && !crate::creature::has_summoning_sickness(card)
+                && !crate::tappable::is_tapped(card))
+            .filter(|(_, card)| match self
+            {
+                Strategy::Aggressive => true,
+                Strategy::Cautious => !defender_battlefield.iter()
+                    .any(|blocker| can_block(gs, blocker) && power(gs, blocker) >= toughness(gs, card)),
+            })
+            .map(|(i, _)| (i, target))
+            .collect()
+    }
+
+    /// Blocker (index into the defending team's permanents) -> attacker (current player battlefield index).
+    pub fn choose_blockers(&self, gs: &GameState) -> HashMap<usize, usize>
+    {
+        let battlefield = gs.zones().get(&Zone::Battlefield).unwrap();
+        let defender = gs.defending_player_index();
+        let defending = gs.defending_permanents(defender);
+        // Creatures blocking for a teammate already count as used
+        let busy = gs.blocking_for_others(defender);
+        let mut used_blockers: HashSet<usize> = (0..defending.len()).filter(|i| busy.contains(&defending[*i])).collect();
+        let mut blocks = HashMap::new();
+
+        for attacker_idx in gs.attackers_targeting(defender)
+        {
+            let Some(attacker) = battlefield.get(attacker_idx) else { continue };
+
+            let chosen = defending.iter().map(|&(player, index)| &gs.players[player].zones[&Zone::Battlefield][index])
+                .enumerate().find(|(blocker_idx, blocker)|
+            {
+                if used_blockers.contains(blocker_idx) || !can_block(gs, blocker)
+                {
+                    return false;
+                }
+                let kills = power(gs, blocker) >= toughness(gs, attacker);
+                match self
+                {
+                    Strategy::Aggressive => kills,
+                    Strategy::Cautious => kills || toughness(gs, blocker) > power(gs, attacker),
+                }
+            });
+
+            if let Some((blocker_idx, _)) = chosen
+            {
+                used_blockers.insert(blocker_idx);
+                blocks.insert(blocker_idx, attacker_idx);
+            }
+        }
+
+        blocks
+    }
+
+    /// Hand indices to discard down to the maximum hand size: spare lands first (one is kept for
+    /// the next land drop), then the most expensive spells.
+    pub fn choose_discards(&self, gs: &GameState, count: usize) -> Vec<usize>
+    {
+        let hand = gs.zones().get(&Zone::Hand).unwrap();
+        let kept_land = hand.iter().position(|c| c.is_type(CardType::Land));
+        let mut order: Vec<usize> = (0..hand.len()).collect();
+        order.sort_by_key(|&i| match hand[i].is_type(CardType::Land)
+        {
+            true if Some(i) == kept_land => (2, std::cmp::Reverse(0)),
+            true => (0, std::cmp::Reverse(0)),
+            false => (1, std::cmp::Reverse(hand[i].cost)),
+        });
+        order.truncate(count);
+        order
+    }
+
+    /// Lands this seat wants to have in play and in hand before it would rather draw spells.
+    fn lands_wanted(&self) -> usize
+    {
+        match self
+        {
+            Strategy::Aggressive => 4,
+            Strategy::Cautious => 5,
+        }
+    }
+
+    /// Which of the cards looked at (top first) to keep on top of the library, in order: lands
+    /// until there are enough, and spells the lands will soon pay for.
+    pub fn choose_kept_on_top(&self, gs: &GameState, player: usize, cards: &[&Card]) -> Vec<usize>
+    {
+        let lands_in_play_or_hand = CardFilter::Type(CardType::Land).and(CardFilter::Controller(TargetController::You))
+            .and(CardFilter::Zone(Zone::Battlefield).or(CardFilter::Zone(Zone::Hand)));
+        let mut lands = gs.count_matching(&lands_in_play_or_hand, player);
+        let mut kept = Vec::new();
+        for (i, card) in cards.iter().enumerate()
+        {
+            let is_land = card.is_type(CardType::Land);
+            if (is_land && lands < self.lands_wanted()) || (!is_land && card.cost as usize <= lands + 1)
+            {
+                lands += is_land as usize;
+                kept.push(i);
+            }
+        }
+        kept
+    }
+
+    /// Which card to take from a library search: the most expensive one castable by next turn,
+    /// or failing that the cheapest.
+    pub fn choose_search(&self, gs: &GameState, player: usize, candidates: &[&Card]) -> Option<usize>
+    {
+        let lands = gs.players[player].zones[&Zone::Battlefield].iter().filter(|c| c.is_type(CardType::Land)).count();
+        candidates.iter().enumerate()
+            .max_by_key(|(_, card)|
+            {
+                let soon = card.cost as usize <= lands + 1;
+                (soon, if soon { card.cost as i64 } else { -(card.cost as i64) })
+            })
+            .map(|(i, _)| i)
+    }
+
+    /// Targets for an ability this seat controls: harmful effects go at opponents and their
+    /// biggest creatures, helpful ones at this seat's own.
+    pub fn choose_targets(&self, gs: &GameState, item: &StackItem) -> Vec<Target>
+    {
+        let Some(spec) = item.target_spec else { return Vec::new() };
+        let harmful = item.effect.is_harmful();
+        let team = gs.team_of(item.controller);
+        let (ours, power_of) = (
+            |target: &Target| match *target
+            {
+                Target::Player(player) => gs.team_of(player) == team,
+                Target::Card(id) => gs.find_card(id).is_some_and(|(player, _, _)| gs.team_of(player) == team),
+            },
+            |target: &Target| match *target
+            {
+                Target::Player(_) => 0,
+                Target::Card(id) => gs.find_card(id).map_or(0, |(_, _, card)| power(gs, card)),
+            },
+        );
+
+        let mut targets = gs.legal_targets(item.controller, &spec, &item.source_types);
+        targets.sort_by_key(|t| (ours(t) == harmful, std::cmp::Reverse(power_of(t))));
+        // Only as many of the wrong side's as needed to reach the minimum
+        let preferred = targets.iter().filter(|t| ours(t) != harmful).count();
+        targets.truncate(spec.max.min(preferred.max(spec.min)));
+        targets
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::card::{Deck, forest, grizzly_bears};
+    use crate::game::GameStep;
+
+    #[test]
+    fn cautious_holds_back_into_untapped_blockers()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        gs.players[0].zones.insert(Zone::Battlefield, vec![grizzly_bears()]);
+        gs.players[1].zones.insert(Zone::Battlefield, vec![grizzly_bears()]);
+        gs.step = GameStep::DeclareAttackers;
+
+        assert_eq!(Strategy::Aggressive.choose_attacks(&gs), vec![(0, 1)]);
+        assert!(Strategy::Cautious.choose_attacks(&gs).is_empty());
+
+        crate::tappable::set_tapped(&mut gs.players[1].zones.get_mut(&Zone::Battlefield).unwrap()[0], true);
+        assert_eq!(Strategy::Cautious.choose_attacks(&gs), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn cleanup_discards_spare_lands_then_expensive_spells()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        let hand = vec![grizzly_bears(), forest(), crate::card::llanowar_elves(), forest(), forest(), grizzly_bears(), forest(), grizzly_bears(), forest()];
+        gs.players[0].zones.insert(Zone::Hand, hand);
+        assert_eq!(Strategy::Aggressive.choose_discards(&gs, 5), vec![3, 4, 6, 8, 0]);
+
+        gs.step = GameStep::Cleanup;
+        gs.step();
+        let hand = &gs.players[0].zones[&Zone::Hand];
+        assert_eq!(hand.len(), 7);
+        assert_eq!(hand.iter().filter(|c| c.is_type(CardType::Land)).count(), 3);
+        assert_eq!(gs.players[0].cards_discarded, 2);
+        assert_eq!(gs.step, GameStep::EndTurn);
+
+        // No limit, no discard
+        gs.rules.max_hand_size = None;
+        gs.players[1].zones.insert(Zone::Hand, vec![forest(); 9]);
+        gs.current_player_index = 1;
+        gs.step = GameStep::Cleanup;
+        gs.step();
+        assert_eq!(gs.players[1].zones[&Zone::Hand].len(), 9);
+    }
+}
@@ -0,0 +1,249 @@
+use serde::{Serialize, Deserialize};
+
+use crate::card::{Card, CardId, CardType, Keyword};
+use crate::filter::CardFilter;
+use crate::game::{GameState, Zone};
+use crate::rules::RulesError;
+use crate::triggers::StackItem;
+
+/// Whose things may be chosen, relative to the ability's controller.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TargetController
+{
+    Any,
+    You,
+    Opponent,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TargetKind
+{
+    Player { controller: TargetController },
+    /// A permanent, optionally only of one card type
+    Permanent { card_type: Option<CardType>, controller: TargetController },
+    /// A card in a graveyard; `controller` is matched against the graveyard's owner
+    GraveyardCard { card_type: Option<CardType>, controller: TargetController },
+}
+
+/// What an ability may target and how many. "Up to two target lands you control" is
+/// `Permanent { card_type: Some(Land), controller: You }` with `min` 0 and `max` 2.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TargetSpec
+{
+    pub kind: TargetKind,
+    pub min: usize,
+    pub max: usize,
+}
+
+impl TargetKind
+{
+    /// The cards this kind of target covers, seen by the ability's controller; None for players.
+    pub fn card_filter(&self) -> Option<CardFilter>
+    {
+        let (zone, card_type, controller) = match *self
+        {
+            TargetKind::Player { .. } => return None,
+            TargetKind::Permanent { card_type, controller } => (Zone::Battlefield, card_type, controller),
+            TargetKind::GraveyardCard { card_type, controller } => (Zone::Graveyard, card_type, controller),
+        };
+        let filter = CardFilter::Zone(zone).and(CardFilter::Controller(controller));
+        Some(match card_type
+        {
+            Some(t) => filter.and(CardFilter::Type(t)),
+            None => filter,
+        })
+    }
+}
+
+impl TargetSpec
+{
+    pub fn one(kind: TargetKind) -> Self
+    {
+        TargetSpec { kind, min: 1, max: 1 }
+    }
+
+    pub fn up_to(count: usize, kind: TargetKind) -> Self
+    {
+        TargetSpec { kind, min: 0, max: count }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Target
+{
+    Player(usize),
+    Card(CardId),
+}
+
+impl GameState
+{
+    /// The player whose battlefield holds the card with this id, and the card.
+    pub fn find_permanent(&self, id: CardId) -> Option<(usize, &Card)>
+    {
+        self.players.iter().enumerate().find_map(|(player, p)|
+            p.zones.get(&Zone::Battlefield).and_then(|bf| bf.iter().find(|c| c.id == id)).map(|card| (player, card))
+        )
+    }
+
+    fn controller_matches(&self, wanted: TargetController, player: usize, controller: usize) -> bool
+    {
+        match wanted
+        {
+            TargetController::Any => true,
+            TargetController::You => player == controller,
+            TargetController::Opponent => self.team_of(player) != self.team_of(controller),
+        }
+    }
+
+    // Permanents can be protected from targeting; cards in other zones can't
+    fn can_target_card(&self, card: &Card, holder: usize, zone: Zone, controller: usize, source_types: &[CardType]) -> bool
+    {
+        zone != Zone::Battlefield || self.can_be_targeted(card, holder, controller, source_types)
+    }
+
+    /// Hexproof, shroud and protection: whether an ability `controller` controls, from a source
+    /// with `source_types`, may target `card` on `card_controller`'s battlefield.
+    pub fn can_be_targeted(&self, card: &Card, card_controller: usize, controller: usize, source_types: &[CardType]) -> bool
+    {
+        crate::keywords::keywords(card).iter().all(|keyword| match keyword
+        {
+            Keyword::Shroud => false,
+            Keyword::Hexproof => self.team_of(card_controller) == self.team_of(controller),
+            Keyword::ProtectionFrom(card_type) => !source_types.contains(card_type),
+        })
+    }
+
+    /// Whether `target` fits `spec` for an ability `controller` controls. Checked when targets are
+    /// chosen and again when the ability resolves.
+    pub fn is_legal_target(&self, controller: usize, spec: &TargetSpec, source_types: &[CardType], target: Target) -> bool
+    {
+        match (spec.kind, target)
+        {
+            (TargetKind::Player { controller: wanted }, Target::Player(player)) =>
+                self.is_alive(player) && self.controller_matches(wanted, player, controller),
+            (kind, Target::Card(id)) =>
+            {
+                let (Some(filter), Some((holder, zone, card))) = (kind.card_filter(), self.find_card(id)) else { return false };
+                self.card_matches(&filter, controller, card, holder, zone)
+                    && self.can_target_card(card, holder, zone, controller, source_types)
+            }
+            _ => false,
+        }
+    }
+
+    /// Every target `spec` allows right now: players in seat order, then permanents, then
+    /// graveyard cards.
+    pub fn legal_targets(&self, controller: usize, spec: &TargetSpec, source_types: &[CardType]) -> Vec<Target>
+    {
+        let players = (0..self.players.len()).map(Target::Player)
+            .filter(|&target| self.is_legal_target(controller, spec, source_types, target));
+        let cards = spec.kind.card_filter().map(|filter| self.cards_matching(&filter, controller)).unwrap_or_default().into_iter()
+            .filter(|&(holder, zone, card)| self.can_target_card(card, holder, zone, controller, source_types))
+            .map(|(_, _, card)| Target::Card(card.id));
+        players.chain(cards).collect()
+    }
+
+    /// Check targets chosen for `item`: as many as its spec allows, all legal, none twice.
+    pub fn check_targets(&self, item: &StackItem, targets: &[Target]) -> Result<(), RulesError>
+    {
+        let spec = item.target_spec.ok_or(RulesError::NoTargetsPending)?;
+        if targets.len() < spec.min || targets.len() > spec.max
+        {
+            return Err(RulesError::WrongTargetCount { min: spec.min, max: spec.max, chosen: targets.len() });
+        }
+        for (i, &target) in targets.iter().enumerate()
+        {
+            if targets[..i].contains(&target) || !self.is_legal_target(item.controller, &spec, &item.source_types, target)
+            {
+                return Err(RulesError::IllegalTarget(target));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::card::{Deck, forest, grizzly_bears};
+    use crate::events::GameEvent;
+    use crate::keywords::add_keyword;
+    use crate::strategy::PlayerControl;
+    use crate::triggers::{AbilityEffect, TriggerCondition, TriggeredAbility, add_triggered_ability};
+
+    fn shock_on_entry() -> Card
+    {
+        let mut card = grizzly_bears();
+        add_triggered_ability(&mut card, TriggeredAbility
+        {
+            condition: TriggerCondition::EntersBattlefield,
+            effect: AbilityEffect::DamageTargets(2),
+            target: Some(TargetSpec::one(TargetKind::Permanent { card_type: Some(CardType::Creature), controller: TargetController::Any })),
+        });
+        card
+    }
+
+    #[test]
+    fn hexproof_shroud_and_protection_limit_targets()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        let mut hexproof = grizzly_bears();
+        add_keyword(&mut hexproof, Keyword::Hexproof);
+        let mut shroud = grizzly_bears();
+        add_keyword(&mut shroud, Keyword::Shroud);
+        let mut protected = grizzly_bears();
+        add_keyword(&mut protected, Keyword::ProtectionFrom(CardType::Creature));
+        let ids = [hexproof.id, shroud.id, protected.id];
+        gs.players[1].zones.insert(Zone::Battlefield, vec![hexproof, shroud, protected, forest()]);
+
+        let creature = TargetSpec::one(TargetKind::Permanent { card_type: Some(CardType::Creature), controller: TargetController::Any });
+        let from_land = gs.legal_targets(0, &creature, &[CardType::Land]);
+        assert_eq!(from_land, vec![Target::Card(ids[2])]);
+        assert_eq!(gs.legal_targets(0, &creature, &[CardType::Creature]), Vec::new());
+
+        // Hexproof only stops opponents
+        assert_eq!(gs.legal_targets(1, &creature, &[CardType::Creature]), vec![Target::Card(ids[0])]);
+
+        let opponent = TargetSpec::one(TargetKind::Player { controller: TargetController::Opponent });
+        assert_eq!(gs.legal_targets(0, &opponent, &[]), vec![Target::Player(1)]);
+    }
+
+    #[test]
+    fn targets_are_chosen_then_rechecked_on_resolution()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        gs.set_record_events(true);
+        let (mine, theirs) = (grizzly_bears(), grizzly_bears());
+        let theirs_id = theirs.id;
+        gs.players[0].zones.insert(Zone::Battlefield, vec![mine]);
+        gs.players[1].zones.insert(Zone::Battlefield, vec![theirs]);
+
+        // An AI aims the damage at the opponent's creature
+        gs.enter_battlefield(0, shock_on_entry());
+        gs.resolve_triggers();
+        assert!(gs.players[1].zones[&Zone::Battlefield].is_empty());
+        assert_eq!(gs.players[1].zones[&Zone::Graveyard].len(), 1);
+
+        // A human is asked, and only legal choices are accepted
+        gs.set_player_control(0, PlayerControl::Human);
+        let victim = grizzly_bears();
+        let victim_id = victim.id;
+        gs.players[1].zones.get_mut(&Zone::Battlefield).unwrap().push(victim);
+        gs.enter_battlefield(0, shock_on_entry());
+        gs.resolve_triggers();
+        assert!(gs.waiting_for_targets && gs.is_waiting_for_decision());
+        assert_eq!(gs.pass(), Err(RulesError::TargetsPending));
+        assert_eq!(gs.choose_targets(vec![Target::Card(theirs_id)]), Err(RulesError::IllegalTarget(Target::Card(theirs_id))));
+        assert!(matches!(gs.choose_targets(Vec::new()), Err(RulesError::WrongTargetCount { .. })));
+
+        // The target leaves before the ability resolves, so it fizzles
+        gs.pending_triggers[0].targets = Some(vec![Target::Card(victim_id)]);
+        gs.waiting_for_targets = false;
+        gs.destroy_permanent(victim_id);
+        gs.drain_events();
+        gs.resolve_triggers();
+        assert_eq!(gs.drain_events(), vec![GameEvent::AbilityFizzled { player: 0, card: String::from("Grizzly Bears") }]);
+        assert_eq!(gs.players[0].zones[&Zone::Battlefield].len(), 3);
+    }
+}
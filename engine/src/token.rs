@@ -0,0 +1,152 @@
+use serde::{Serialize, Deserialize};
+
+use crate::card::{Card, CardType, CountersFragment, CreatureFragment, CreatureStats, TappableFragment};
+use crate::fragments::Fragments;
+use crate::events::GameEvent;
+use crate::game::GameState;
+use crate::rules::RulesError;
+
+/// What a token looks like when it is created: the values a card would have printed on it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TokenTemplate
+{
+    pub name: String,
+    pub card_types: Vec<CardType>,
+    pub stats: Option<CreatureStats>,
+    // Any further fragments (abilities) every token from this template starts with
+    #[serde(default)]
+    pub fragments: Fragments,
+}
+
+impl TokenTemplate
+{
+    pub fn creature(name: &str, power: u8, toughness: u8) -> Self
+    {
+        TokenTemplate
+        {
+            name: String::from(name),
+            card_types: vec![CardType::Creature],
+            stats: Some(CreatureStats { power, toughness }),
+            fragments: Fragments::default(),
+        }
+    }
+
+    /// A fresh token built from this template. Every token is a tappable permanent.
+    pub fn card(&self) -> Card
+    {
+        let mut card = Card::new(&self.name, 0);
+        card.is_token = true;
+        card.set_builtin(TappableFragment { tapped: false });
+        // Through add_type so a template's types and stats can't disagree
+        for &card_type in &self.card_types
+        {
+            card.add_type(card_type);
+        }
+        if let Some(stats) = self.stats
+        {
+            card.add_type(CardType::Creature);
+            card.set_builtin(CreatureFragment { stats, summoning_sickness: false });
+        }
+        card.merge_fragments(&self.fragments);
+        card
+    }
+}
+
+impl GameState
+{
+    /// Put a token onto `player`'s battlefield and return its index there. Creature tokens have
+    /// summoning sickness like any creature that just arrived.
+    pub fn put_token(&mut self, player: usize, mut token: Card) -> usize
+    {
+        token.is_token = true;
+        crate::creature::set_summoning_sickness(&mut token, true);
+        self.emit(GameEvent::TokenCreated { player, card: token.name.clone() });
+        self.enter_battlefield(player, token)
+    }
+
+    /// Create `count` tokens from `template` for `player`, returning their battlefield indices.
+    pub fn create_tokens(&mut self, player: usize, template: &TokenTemplate, count: usize) -> Vec<usize>
+    {
+        (0..count).map(|_| self.put_token(player, template.card())).collect()
+    }
+
+    /// Create a token for `player` that copies the permanent at `index` on `owner`'s battlefield.
+    pub fn create_token_copy(&mut self, player: usize, owner: usize, index: usize) -> Result<usize, RulesError>
+    {
+        let copy = self.permanent(owner, index)?.copiable();
+        Ok(self.put_token(player, copy))
+    }
+
+    /// Have the permanent at `index` on `player`'s battlefield become a copy of the one at
+    /// `source_index` on `source_owner`'s. It takes the copiable values but keeps its own status:
+    /// its id, tapped or not, summoning sickness, counters and whether it is a token.
+    pub fn become_copy(&mut self, player: usize, index: usize, source_owner: usize, source_index: usize) -> Result<(), RulesError>
+    {
+        let mut copy = self.permanent(source_owner, source_index)?.copiable();
+        let target = self.permanent_mut(player, index)?;
+        copy.id = target.id;
+        copy.is_token = target.is_token;
+        crate::tappable::set_tapped(&mut copy, crate::tappable::is_tapped(target));
+        crate::creature::set_summoning_sickness(&mut copy, crate::creature::has_summoning_sickness(target));
+        if let Some(counters) = target.remove::<CountersFragment>()
+        {
+            copy.set_builtin(counters);
+        }
+
+        *target = copy;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::collections::HashMap;
+    use super::*;
+    use crate::card::{Deck, forest, grizzly_bears};
+    use crate::game::{GameStep, Zone};
+
+    #[test]
+    fn tokens_enter_sick_and_vanish_when_they_die()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        let indices = gs.create_tokens(1, &TokenTemplate::creature("Saproling", 1, 1), 2);
+        assert_eq!(indices, vec![0, 1]);
+        let saproling = &gs.players[1].zones[&Zone::Battlefield][0];
+        assert!(saproling.is_token && crate::creature::has_summoning_sickness(saproling));
+
+        // A bear attacks and one Saproling blocks and dies
+        gs.players[0].zones.insert(Zone::Battlefield, vec![grizzly_bears()]);
+        gs.step = GameStep::DeclareAttackers;
+        gs.declare_attackers(vec![0]).unwrap();
+        gs.declare_blockers(HashMap::from([(0, 0)])).unwrap();
+        gs.step();
+
+        assert_eq!(gs.players[1].zones[&Zone::Battlefield].len(), 1);
+        assert!(gs.players[1].zones[&Zone::Graveyard].is_empty());
+    }
+
+    #[test]
+    fn copies_take_copiable_values_but_keep_status()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        let mut tapped_bears = grizzly_bears();
+        crate::tappable::set_tapped(&mut tapped_bears, true);
+        gs.players[0].zones.insert(Zone::Battlefield, vec![tapped_bears, forest()]);
+
+        // A token copy of a tapped creature enters untapped
+        let index = gs.create_token_copy(1, 0, 0).unwrap();
+        let copy = &gs.players[1].zones[&Zone::Battlefield][index];
+        assert_eq!(copy.name, "Grizzly Bears");
+        assert!(copy.is_token && !crate::tappable::is_tapped(copy));
+
+        // The Forest becomes a bear but stays a nontoken card that is untapped
+        gs.become_copy(0, 1, 0, 0).unwrap();
+        let forest = &gs.players[0].zones[&Zone::Battlefield][1];
+        assert_eq!(forest.name, "Grizzly Bears");
+        assert!(!forest.is_token && !crate::tappable::is_tapped(forest));
+        assert_eq!(crate::creature::creature_stats(forest).unwrap().power, 2);
+
+        assert!(matches!(gs.become_copy(0, 5, 0, 0), Err(RulesError::InvalidIndex(5))));
+    }
+}
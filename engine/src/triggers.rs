@@ -0,0 +1,433 @@
+use serde::{Serialize, Deserialize};
+
+use crate::card::{Card, CardId, CardType, CounterKind, TriggersFragment};
+use crate::effects::{Affected, EffectDuration, Modification};
+use crate::events::GameEvent;
+use crate::filter::CardFilter;
+use crate::game::{GameState, GameStep, Zone};
+use crate::strategy::PlayerControl;
+use crate::targeting::{Target, TargetSpec};
+use crate::token::TokenTemplate;
+
+// Abilities resolved in one go before giving up, so a trigger loop can't hang the game
+const MAX_RESOLUTIONS: usize = 1000;
+
+/// When a triggered ability triggers.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TriggerCondition
+{
+    /// When this permanent enters the battlefield
+    EntersBattlefield,
+    /// When this creature dies; seen from the graveyard, looking back
+    Dies,
+    /// Whenever another creature dies. `yours` limits it to creatures the same player controlled.
+    CreatureDies { yours: bool },
+    /// At the beginning of its controller's upkeep
+    BeginningOfUpkeep,
+    /// At the beginning of combat on its controller's turn
+    BeginningOfCombat,
+    /// At the beginning of its controller's end step
+    BeginningOfEndStep,
+}
+
+/// What an ability does when it resolves, from its controller's point of view.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum AbilityEffect
+{
+    GainLife(i32),
+    EachOpponentLosesLife(i32),
+    DrawCards(u32),
+    CreateTokens { template: TokenTemplate, count: usize },
+    /// Counters on the ability's source, if it is still on the battlefield
+    PutCounters { kind: CounterKind, count: u32 },
+    /// Damage to each target player or creature
+    DamageTargets(i32),
+    DestroyTargets,
+    /// Target creatures get +power/+toughness until end of turn
+    PumpTargets { power: i32, toughness: i32 },
+    AddMana(u32),
+    /// Target cards move to `to`: exile a permanent, or return a card from a graveyard
+    MoveTargets { to: Zone },
+    Scry(u32),
+    Surveil(u32),
+    /// Search the library for a card passing `filter`, put it into `to` and shuffle
+    SearchLibrary { filter: CardFilter, to: Zone },
+}
+
+impl AbilityEffect
+{
+    /// Whether the effect hurts whatever it touches, so an AI aims it at opponents.
+    pub fn is_harmful(&self) -> bool
+    {
+        matches!(self, AbilityEffect::EachOpponentLosesLife(_) | AbilityEffect::DamageTargets(_) | AbilityEffect::DestroyTargets)
+            || matches!(self, AbilityEffect::MoveTargets { to: Zone::Exile })
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TriggeredAbility
+{
+    pub condition: TriggerCondition,
+    pub effect: AbilityEffect,
+    #[serde(default)]
+    pub target: Option<TargetSpec>,
+}
+
+/// A triggered ability on its way to resolving.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StackItem
+{
+    pub controller: usize,
+    pub source: CardId,
+    pub source_name: String,
+    pub effect: AbilityEffect,
+    /// The source's types, for protection
+    #[serde(default)]
+    pub source_types: Vec<CardType>,
+    #[serde(default)]
+    pub target_spec: Option<TargetSpec>,
+    /// Chosen as the ability goes on the stack
+    #[serde(default)]
+    pub targets: Option<Vec<Target>>,
+}
+
+pub fn triggered_abilities(card: &Card) -> &[TriggeredAbility]
+{
+    card.get::<TriggersFragment>()
+        .map(|tf| tf.abilities.as_slice())
+        .unwrap_or(&[])
+}
+
+pub fn add_triggered_ability(card: &mut Card, ability: TriggeredAbility)
+{
+    card.builtin_or_default::<TriggersFragment>().abilities.push(ability);
+}
+
+// Abilities of `card` whose condition matches, controlled by `controller`
+fn triggered_by(controller: usize, card: &Card, matches: impl Fn(&TriggerCondition) -> bool) -> Vec<StackItem>
+{
+    triggered_abilities(card).iter()
+        .filter(|ability| matches(&ability.condition))
+        .map(|ability| StackItem
+        {
+            controller,
+            source: card.id,
+            source_name: card.name.clone(),
+            effect: ability.effect.clone(),
+            source_types: card.card_types().to_vec(),
+            target_spec: ability.target,
+            targets: None,
+        })
+        .collect()
+}
+
+impl GameState
+{
+    /// Queue the abilities of permanents on the battlefield that trigger on `event`.
+    pub fn collect_triggers(&mut self, event: &GameEvent)
+    {
+        let matches = |controller: usize, condition: &TriggerCondition| match (event, condition)
+        {
+            (GameEvent::CreatureDied { player, .. }, TriggerCondition::CreatureDies { yours }) => !yours || *player == controller,
+            (GameEvent::StepChanged { player, step: GameStep::Upkeep }, TriggerCondition::BeginningOfUpkeep)
+            | (GameEvent::StepChanged { player, step: GameStep::BeginCombat }, TriggerCondition::BeginningOfCombat)
+            | (GameEvent::StepChanged { player, step: GameStep::EndStep }, TriggerCondition::BeginningOfEndStep) => *player == controller,
+            _ => false,
+        };
+        if !matches!(event, GameEvent::CreatureDied { .. }
+            | GameEvent::StepChanged { step: GameStep::Upkeep | GameStep::BeginCombat | GameStep::EndStep, .. })
+        {
+            return;
+        }
+
+        let mut triggered = Vec::new();
+        for controller in self.living_players()
+        {
+            for card in self.players[controller].zones.get(&Zone::Battlefield).unwrap()
+            {
+                triggered.extend(triggered_by(controller, card, |condition| matches(controller, condition)));
+            }
+        }
+        self.pending_triggers.extend(triggered);
+    }
+
+    /// Put a card onto `player`'s battlefield and return its index there. Its enters-the-battlefield
+    /// abilities trigger.
+    pub fn enter_battlefield(&mut self, player: usize, card: Card) -> usize
+    {
+        self.pending_triggers.extend(triggered_by(player, &card, |condition| *condition == TriggerCondition::EntersBattlefield));
+        let battlefield = self.players[player].zones.get_mut(&Zone::Battlefield).unwrap();
+        battlefield.push(card);
+        battlefield.len() - 1
+    }
+
+    /// A creature `player` controlled has died and is leaving the battlefield, so its "when this
+    /// dies" abilities trigger. move_card calls this; the card itself is moved there.
+    pub(crate) fn creature_died(&mut self, player: usize, card: &Card)
+    {
+        self.pending_triggers.extend(triggered_by(player, card, |condition| *condition == TriggerCondition::Dies));
+        self.emit(GameEvent::CreatureDied { player, card: card.name.clone() });
+    }
+
+    /// Destroy the permanent with this id, if it is still on the battlefield.
+    pub fn destroy_permanent(&mut self, id: CardId)
+    {
+        let _ = self.move_card(id, Zone::Battlefield, Zone::Graveyard);
+    }
+
+    /// Put waiting triggers on the stack in APNAP order: the active player's go on first, so they
+    /// resolve last, then everyone else's in turn order. Targets are chosen on the way; returns
+    /// false if a human has to choose them first.
+    fn stack_pending_triggers(&mut self) -> bool
+    {
+        let n = self.players.len();
+        let active = self.current_player_index;
+        let mut pending = std::mem::take(&mut self.pending_triggers);
+        pending.sort_by_key(|item| (item.controller + n - active) % n);
+
+        let mut pending = pending.into_iter();
+        while let Some(mut item) = pending.next()
+        {
+            if let Some(spec) = item.target_spec && item.targets.is_none()
+            {
+                // Without enough legal targets the ability is removed instead
+                if self.legal_targets(item.controller, &spec, &item.source_types).len() < spec.min
+                {
+                    continue;
+                }
+                match self.players[item.controller].control
+                {
+                    PlayerControl::Ai(strategy) => item.targets = Some(strategy.choose_targets(self, &item)),
+                    PlayerControl::Human =>
+                    {
+                        // This ability and the ones after it wait for the choice
+                        self.pending_triggers = std::iter::once(item).chain(pending).collect();
+                        self.waiting_for_targets = true;
+                        return false;
+                    }
+                }
+            }
+            self.stack.push(item);
+        }
+        true
+    }
+
+    /// Resolve the stack, top first, until it and the queue of new triggers are both empty.
+    pub fn resolve_triggers(&mut self)
+    {
+        let mut resolved = 0;
+        loop
+        {
+            // Resolving stops at a library choice a human is making, and picks up once it is made
+            if self.library_choice.is_some() || !self.stack_pending_triggers()
+            {
+                return;
+            }
+            if self.is_game_over() || resolved >= MAX_RESOLUTIONS
+            {
+                self.stack.clear();
+                break;
+            }
+            let Some(item) = self.stack.pop() else { break };
+            self.resolve_ability(item);
+            resolved += 1;
+        }
+
+        // A player knocked out on their own turn has no more of it
+        if !self.is_alive(self.current_player_index)
+        {
+            self.end_turn_early();
+        }
+    }
+
+    fn resolve_ability(&mut self, item: StackItem)
+    {
+        let controller = item.controller;
+        let mut targets = item.targets.clone().unwrap_or_default();
+        if let Some(spec) = item.target_spec
+        {
+            // Targets are checked again; if none of them is still legal the ability does nothing
+            let chosen = targets.len();
+            targets.retain(|&target| self.is_legal_target(controller, &spec, &item.source_types, target));
+            if chosen > 0 && targets.is_empty()
+            {
+                self.emit(GameEvent::AbilityFizzled { player: controller, card: item.source_name.clone() });
+                return;
+            }
+        }
+        self.emit(GameEvent::AbilityResolved { player: controller, card: item.source_name.clone() });
+
+        match item.effect
+        {
+            AbilityEffect::GainLife(amount) => self.gain_life(controller, amount),
+            AbilityEffect::EachOpponentLosesLife(amount) =>
+            {
+                for opponent in self.opponents_of(controller)
+                {
+                    self.lose_life(opponent, amount);
+                }
+            }
+            AbilityEffect::DrawCards(count) =>
+            {
+                for _ in 0..count
+                {
+                    if !self.draw_card(controller)
+                    {
+                        // Drawing from an empty library loses the game
+                        self.eliminate(controller);
+                        break;
+                    }
+                }
+            }
+            AbilityEffect::CreateTokens { template, count } =>
+            {
+                self.create_tokens(controller, &template, count);
+            }
+            AbilityEffect::PutCounters { kind, count } =>
+            {
+                let source = self.controller_of(item.source).and_then(|player|
+                {
+                    let battlefield = self.players[player].zones.get(&Zone::Battlefield).unwrap();
+                    battlefield.iter().position(|c| c.id == item.source).map(|index| (player, index))
+                });
+                if let Some((player, index)) = source
+                {
+                    let _ = self.put_counters(player, index, kind, count);
+                }
+            }
+            AbilityEffect::DamageTargets(amount) =>
+            {
+                for target in targets
+                {
+                    match target
+                    {
+                        Target::Player(player) => self.lose_life(player, amount),
+                        Target::Card(id) =>
+                        {
+                            let lethal = self.find_permanent(id)
+                                .and_then(|(_, card)| self.effective_stats(card))
+                                .is_some_and(|stats| stats.toughness as i32 <= amount);
+                            if lethal
+                            {
+                                self.destroy_permanent(id);
+                            }
+                        }
+                    }
+                }
+            }
+            AbilityEffect::DestroyTargets =>
+            {
+                for target in targets
+                {
+                    if let Target::Card(id) = target
+                    {
+                        self.destroy_permanent(id);
+                    }
+                }
+            }
+            AbilityEffect::PumpTargets { power, toughness } =>
+            {
+                for target in targets
+                {
+                    if let Target::Card(id) = target
+                    {
+                        self.add_effect(Some(item.source), Affected::Card(id), Modification::ModifyStats { power, toughness }, EffectDuration::UntilEndOfTurn);
+                    }
+                }
+            }
+            AbilityEffect::AddMana(amount) => self.add_mana(controller, amount),
+            AbilityEffect::MoveTargets { to } =>
+            {
+                for target in targets
+                {
+                    if let Target::Card(id) = target
+                    {
+                        let _ = self.move_card_to(id, to);
+                    }
+                }
+            }
+            AbilityEffect::Scry(count) => self.scry(controller, count as usize),
+            AbilityEffect::Surveil(count) => self.surveil(controller, count as usize),
+            AbilityEffect::SearchLibrary { filter, to } =>
+            {
+                self.search_library(controller, &filter, to);
+            }
+        }
+
+        self.check_zero_toughness();
+        self.check_eliminations();
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::card::{Deck, forest, grizzly_bears};
+
+    fn with_trigger(mut card: Card, condition: TriggerCondition, effect: AbilityEffect) -> Card
+    {
+        add_triggered_ability(&mut card, TriggeredAbility { condition, effect, target: None });
+        card
+    }
+
+    #[test]
+    fn enter_and_upkeep_triggers_resolve()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: vec![forest(); 20] });
+        let visionary = with_trigger(grizzly_bears(), TriggerCondition::EntersBattlefield, AbilityEffect::DrawCards(1));
+        let herald = with_trigger(forest(), TriggerCondition::BeginningOfUpkeep, AbilityEffect::GainLife(2));
+        gs.enter_battlefield(0, herald);
+
+        gs.enter_battlefield(0, visionary);
+        gs.resolve_triggers();
+        assert_eq!(gs.players[0].zones[&Zone::Hand].len(), 8);
+
+        gs.step(); // Untap
+        gs.step(); // Upkeep
+        assert_eq!(gs.players[0].life, 22);
+        assert!(gs.stack.is_empty() && gs.pending_triggers.is_empty());
+
+        // The other player's upkeep doesn't trigger it
+        gs.current_player_index = 1;
+        gs.collect_triggers(&GameEvent::StepChanged { player: 1, step: GameStep::Upkeep });
+        assert!(gs.pending_triggers.is_empty());
+    }
+
+    #[test]
+    fn simultaneous_triggers_stack_in_apnap_order()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        gs.set_record_events(true);
+        let watcher = |name: &str| {
+            let mut card = with_trigger(grizzly_bears(), TriggerCondition::CreatureDies { yours: false }, AbilityEffect::GainLife(1));
+            card.name = String::from(name);
+            card
+        };
+        gs.enter_battlefield(0, watcher("Active"));
+        gs.enter_battlefield(1, watcher("Nonactive"));
+        let dying = with_trigger(grizzly_bears(), TriggerCondition::Dies, AbilityEffect::CreateTokens
+        {
+            template: TokenTemplate::creature("Spirit", 1, 1),
+            count: 1,
+        });
+
+        let dying_id = dying.id;
+        gs.players[1].zones.get_mut(&Zone::Battlefield).unwrap().push(dying);
+        gs.destroy_permanent(dying_id);
+        gs.drain_events();
+        gs.resolve_triggers();
+
+        // The nonactive player's abilities were stacked last and resolve first
+        let resolved: Vec<GameEvent> = gs.drain_events().into_iter()
+            .filter(|e| matches!(e, GameEvent::AbilityResolved { .. }))
+            .collect();
+        assert_eq!(resolved, vec![
+            GameEvent::AbilityResolved { player: 1, card: String::from("Nonactive") },
+            GameEvent::AbilityResolved { player: 1, card: String::from("Grizzly Bears") },
+            GameEvent::AbilityResolved { player: 0, card: String::from("Active") },
+        ]);
+        assert_eq!(gs.players[1].zones[&Zone::Battlefield].len(), 2);
+        assert_eq!(gs.players[1].zones[&Zone::Graveyard].len(), 1);
+    }
+}
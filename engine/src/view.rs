@@ -0,0 +1,119 @@
+use serde::{Serialize, Deserialize};
+
+use crate::card::Card;
+use crate::events::GameEvent;
+use crate::game::{GameState, Zone};
+
+/// What one viewer may see of a game: every library and the hands of players they can't
+/// see are emptied, with the real sizes kept in `hand_counts` / `library_counts`. The shuffle
+/// seed is zeroed, since it would give away the order of every later shuffle. A viewer making a
+/// library choice sees the cards it is made from in `looking_at`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameView
+{
+    #[serde(flatten)]
+    pub state: GameState,
+    pub hand_counts: Vec<usize>,
+    pub library_counts: Vec<usize>,
+    pub deciding_player_index: usize,
+    #[serde(default)]
+    pub looking_at: Vec<Card>,
+}
+
+impl GameState
+{
+    /// Project the game for a viewer who may see the hands of `visible_hands` (player indices).
+    pub fn view_for(&self, visible_hands: &[usize]) -> GameView
+    {
+        let count = |zone: Zone| -> Vec<usize>
+        {
+            self.players.iter().map(|p| p.zones.get(&zone).map(|cards| cards.len()).unwrap_or(0)).collect()
+        };
+        let hand_counts = count(Zone::Hand);
+        let library_counts = count(Zone::Library);
+
+        let looking_at = match &self.library_choice
+        {
+            Some(choice) if visible_hands.contains(&choice.player()) => self.library_choice_cards(choice).into_iter().cloned().collect(),
+            _ => Vec::new(),
+        };
+
+        let mut state = self.clone();
+        state.events.clear();
+        state.rng_state = 0;
+        for (index, player) in state.players.iter_mut().enumerate()
+        {
+            if let Some(library) = player.zones.get_mut(&Zone::Library)
+            {
+                library.clear();
+            }
+            if !visible_hands.contains(&index)
+                && let Some(hand) = player.zones.get_mut(&Zone::Hand)
+            {
+                hand.clear();
+            }
+        }
+
+        GameView
+        {
+            deciding_player_index: self.deciding_player_index(),
+            state,
+            hand_counts,
+            library_counts,
+            looking_at,
+        }
+    }
+}
+
+/// `events` as a viewer who may see the hands of `visible_hands` may see them: a card moving
+/// between zones hidden from them, such as a search from a library into a hand, loses its name.
+pub fn events_for(events: &[GameEvent], visible_hands: &[usize]) -> Vec<GameEvent>
+{
+    let hidden = |player: usize, zone: Zone| zone == Zone::Library || (zone == Zone::Hand && !visible_hands.contains(&player));
+    events.iter().map(|event| match event
+    {
+        GameEvent::CardMoved { player, from, to, .. } if hidden(*player, *from) && hidden(*player, *to) =>
+            GameEvent::CardMoved { player: *player, card: String::new(), from: *from, to: *to },
+        event => event.clone(),
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn view_hides_libraries_and_other_hands()
+    {
+        let mut gs = GameState::new_default();
+        gs.rng_state = 42;
+        let view = gs.view_for(&[1]);
+
+        assert_eq!(view.hand_counts, vec![7, 7]);
+        assert_eq!(view.library_counts, vec![53, 53]);
+        assert!(view.state.players[0].zones[&Zone::Hand].is_empty());
+        assert_eq!(view.state.players[1].zones[&Zone::Hand].len(), 7);
+        assert!(view.state.players.iter().all(|p| p.zones[&Zone::Library].is_empty()));
+
+        let json = serde_json::to_value(&view).unwrap();
+        assert_eq!(json["rng_state"], 0, "the view must not carry the shuffle seed");
+        assert_eq!(gs.rng_state, 42);
+        assert!(json.get("players").is_some());
+        assert!(json.get("state").is_none());
+    }
+
+    #[test]
+    fn cards_moving_out_of_sight_keep_their_names_hidden()
+    {
+        let searched = GameEvent::CardMoved { player: 0, card: String::from("Grizzly Bears"), from: Zone::Library, to: Zone::Hand };
+        let milled = GameEvent::CardMoved { player: 0, card: String::from("Forest"), from: Zone::Library, to: Zone::Graveyard };
+        let events = vec![searched.clone(), milled.clone()];
+
+        assert_eq!(events_for(&events, &[0]), events);
+        assert_eq!(events_for(&events, &[1]), vec![
+            GameEvent::CardMoved { player: 0, card: String::new(), from: Zone::Library, to: Zone::Hand },
+            milled,
+        ]);
+    }
+}
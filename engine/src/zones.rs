@@ -0,0 +1,148 @@
+use crate::card::{Card, CardId, CardType, CountersFragment};
+use crate::events::GameEvent;
+use crate::game::{GameState, Zone};
+use crate::rules::RulesError;
+
+// Where move_card and find_card look, public zones first
+const ZONES: [Zone; 5] = [Zone::Battlefield, Zone::Graveyard, Zone::Exile, Zone::Hand, Zone::Library];
+
+impl GameState
+{
+    /// The player whose `zone` holds the card with this id, and its index there.
+    pub fn find_in_zone(&self, card: CardId, zone: Zone) -> Option<(usize, usize)>
+    {
+        self.players.iter().enumerate().find_map(|(player, p)|
+            p.zones.get(&zone).and_then(|cards| cards.iter().position(|c| c.id == card)).map(|index| (player, index))
+        )
+    }
+
+    /// The card with this id wherever it is, with its owner and zone.
+    pub fn find_card(&self, card: CardId) -> Option<(usize, Zone, &Card)>
+    {
+        ZONES.iter().find_map(|&zone| self.find_in_zone(card, zone)
+            .map(|(player, index)| (player, zone, &self.players[player].zones[&zone][index])))
+    }
+
+    /// Move a card from one of its owner's zones to another and return its id there. A card that
+    /// changes zones is a new object with a new id, so effects that followed it stop; one leaving
+    /// the battlefield loses its counters and tapped state. A creature put into a graveyard from
+    /// the battlefield dies, and a token that leaves the battlefield ceases to exist (None).
+    pub fn move_card(&mut self, card: CardId, from: Zone, to: Zone) -> Result<Option<CardId>, RulesError>
+    {
+        let (player, index) = self.find_in_zone(card, from).ok_or(RulesError::CardNotInZone { card, zone: from })?;
+        let dies = from == Zone::Battlefield && to == Zone::Graveyard
+            && self.has_effective_type(&self.players[player].zones[&from][index], CardType::Creature);
+
+        let mut moved = self.players[player].zones.get_mut(&from).unwrap().remove(index);
+        if dies
+        {
+            self.creature_died(player, &moved);
+        }
+        self.emit(GameEvent::CardMoved { player, card: moved.name.clone(), from, to });
+
+        if from == Zone::Battlefield
+        {
+            if moved.is_token
+            {
+                return Ok(None);
+            }
+            moved.remove::<CountersFragment>();
+            crate::tappable::set_tapped(&mut moved, false);
+        }
+        moved.id = crate::card::next_card_id();
+        let id = moved.id;
+
+        if to == Zone::Battlefield
+        {
+            crate::creature::set_summoning_sickness(&mut moved, true);
+            self.enter_battlefield(player, moved);
+        }
+        else
+        {
+            self.players[player].zones.get_mut(&to).unwrap().push(moved);
+        }
+        Ok(Some(id))
+    }
+
+    /// Move a card to `to` from whichever zone it is in.
+    pub fn move_card_to(&mut self, card: CardId, to: Zone) -> Result<Option<CardId>, RulesError>
+    {
+        let (_, from, _) = self.find_card(card).ok_or(RulesError::CardNotInZone { card, zone: to })?;
+        self.move_card(card, from, to)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::card::{CounterKind, Deck, forest, grizzly_bears};
+    use crate::targeting::{TargetController, TargetKind, TargetSpec};
+    use crate::triggers::{AbilityEffect, TriggerCondition, TriggeredAbility, add_triggered_ability};
+
+    #[test]
+    fn moving_cards_makes_new_objects_and_records_events()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        gs.set_record_events(true);
+        let mut bears = grizzly_bears();
+        crate::counters::add_counters(&mut bears, CounterKind::PlusOne, 2);
+        let id = bears.id;
+        gs.players[0].zones.insert(Zone::Battlefield, vec![bears]);
+
+        assert_eq!(gs.move_card(id, Zone::Graveyard, Zone::Exile), Err(RulesError::CardNotInZone { card: id, zone: Zone::Graveyard }));
+        let dead = gs.move_card(id, Zone::Battlefield, Zone::Graveyard).unwrap().unwrap();
+        assert_ne!(dead, id);
+        assert_eq!(gs.drain_events(), vec![
+            GameEvent::CreatureDied { player: 0, card: String::from("Grizzly Bears") },
+            GameEvent::CardMoved { player: 0, card: String::from("Grizzly Bears"), from: Zone::Battlefield, to: Zone::Graveyard },
+        ]);
+
+        // Back onto the battlefield without its counters and with summoning sickness
+        let back = gs.move_card_to(dead, Zone::Battlefield).unwrap().unwrap();
+        let (_, zone, card) = gs.find_card(back).unwrap();
+        assert_eq!(zone, Zone::Battlefield);
+        assert_eq!(crate::counters::counter_count(card, &CounterKind::PlusOne), 0);
+        assert!(crate::creature::has_summoning_sickness(card));
+
+        let mut token = forest();
+        token.is_token = true;
+        let token_id = token.id;
+        gs.players[0].zones.get_mut(&Zone::Battlefield).unwrap().push(token);
+        assert_eq!(gs.move_card(token_id, Zone::Battlefield, Zone::Exile), Ok(None));
+        assert!(gs.players[0].zones[&Zone::Exile].is_empty());
+    }
+
+    #[test]
+    fn abilities_exile_permanents_and_return_cards_from_graveyards()
+    {
+        let mut gs = GameState::new(2, &Deck { cards: Vec::new() });
+        let mut gravedigger = grizzly_bears();
+        add_triggered_ability(&mut gravedigger, TriggeredAbility
+        {
+            condition: TriggerCondition::EntersBattlefield,
+            effect: AbilityEffect::MoveTargets { to: Zone::Hand },
+            target: Some(TargetSpec::one(TargetKind::GraveyardCard { card_type: Some(CardType::Creature), controller: TargetController::You })),
+        });
+        let mut banisher = grizzly_bears();
+        add_triggered_ability(&mut banisher, TriggeredAbility
+        {
+            condition: TriggerCondition::EntersBattlefield,
+            effect: AbilityEffect::MoveTargets { to: Zone::Exile },
+            target: Some(TargetSpec::one(TargetKind::Permanent { card_type: None, controller: TargetController::Opponent })),
+        });
+        gs.players[0].zones.insert(Zone::Graveyard, vec![forest(), grizzly_bears()]);
+        gs.players[1].zones.insert(Zone::Battlefield, vec![grizzly_bears()]);
+
+        gs.enter_battlefield(0, gravedigger);
+        gs.resolve_triggers();
+        assert_eq!(gs.players[0].zones[&Zone::Graveyard].len(), 1);
+        assert_eq!(gs.players[0].zones[&Zone::Hand].last().unwrap().name, "Grizzly Bears");
+
+        gs.enter_battlefield(0, banisher);
+        gs.resolve_triggers();
+        assert!(gs.players[1].zones[&Zone::Battlefield].is_empty());
+        assert_eq!(gs.players[1].zones[&Zone::Exile].len(), 1);
+        assert!(gs.players[1].zones[&Zone::Graveyard].is_empty());
+    }
+}
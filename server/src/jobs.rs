@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
+
+use crate::sessions::random_hex;
+
+pub type JobId = String;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum JobStatus {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+/// Where a simulation job is up to, including statistics over the games finished so far.
+#[derive(Clone, Debug, Serialize)]
+pub struct JobProgress {
+    pub id: JobId,
+    pub label: String,
+    pub status: JobStatus,
+    pub completed: u64,
+    pub total: u64,
+    pub avg_turns: f64,
+    pub min_turns: Option<u32>,
+    pub max_turns: Option<u32>,
+    #[serde(skip)]
+    total_turns: u64,
+    // Whatever the job reports once it is done
+    pub result: Option<serde_json::Value>,
+    // Why a failed job stopped
+    pub error: Option<String>,
+}
+
+/// A batch of games running on the blocking pool. The worker records each finished game and
+/// checks `is_cancelled` between games.
+pub struct SimulationJob {
+    cancelled: AtomicBool,
+    progress: Mutex<JobProgress>,
+}
+
+impl SimulationJob {
+    fn new(id: JobId, label: &str, total: u64) -> Self {
+        SimulationJob {
+            cancelled: AtomicBool::new(false),
+            progress: Mutex::new(JobProgress {
+                id,
+                label: label.to_string(),
+                status: JobStatus::Running,
+                completed: 0,
+                total,
+                avg_turns: 0.0,
+                min_turns: None,
+                max_turns: None,
+                total_turns: 0,
+                result: None,
+                error: None,
+            }),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Count one finished game and return how many games are done.
+    pub fn record_game(&self, turns: u32) -> u64 {
+        let mut progress = self.progress.lock().unwrap();
+        progress.total_turns += turns as u64;
+        progress.completed += 1;
+        progress.avg_turns = progress.total_turns as f64 / progress.completed as f64;
+        progress.min_turns = Some(progress.min_turns.map_or(turns, |m| m.min(turns)));
+        progress.max_turns = Some(progress.max_turns.map_or(turns, |m| m.max(turns)));
+        progress.completed
+    }
+
+    /// Mark the job done (or cancelled, if it was asked to stop) with its final result.
+    pub fn finish(&self, result: Option<serde_json::Value>) {
+        let mut progress = self.progress.lock().unwrap();
+        progress.status = if self.is_cancelled() { JobStatus::Cancelled } else { JobStatus::Completed };
+        progress.result = result;
+    }
+
+    /// Run the job's work on this thread and finish with whatever it returns. A panic marks the
+    /// job failed instead of leaving it running forever.
+    pub fn run(&self, work: impl FnOnce() -> Option<serde_json::Value>) {
+        match panic::catch_unwind(AssertUnwindSafe(work)) {
+            Ok(result) => self.finish(result),
+            Err(payload) => {
+                let reason = payload.downcast_ref::<&str>().map(|s| s.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| String::from("the job panicked"));
+                // The panic may have poisoned the lock mid-update; the status still has to change
+                let mut progress = self.progress.lock().unwrap_or_else(|e| e.into_inner());
+                progress.status = JobStatus::Failed;
+                progress.error = Some(reason);
+            }
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.progress.lock().unwrap().status == JobStatus::Running
+    }
+
+    pub fn snapshot(&self) -> JobProgress {
+        self.progress.lock().unwrap().clone()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum JobError {
+    Full { max_jobs: usize },
+}
+
+/// Simulation jobs known to this server. Finished jobs are kept so their results can be read,
+/// and dropped oldest-first to make room for new ones.
+pub struct JobRegistry {
+    jobs: HashMap<JobId, Arc<SimulationJob>>,
+    // Creation order, for dropping the oldest finished job first
+    order: Vec<JobId>,
+    max_jobs: usize,
+}
+
+impl JobRegistry {
+    pub fn new(max_jobs: usize) -> Self {
+        JobRegistry {
+            jobs: HashMap::new(),
+            order: Vec::new(),
+            max_jobs,
+        }
+    }
+
+    /// Register a new running job of `total` games. The caller starts the actual work.
+    pub fn start(&mut self, label: &str, total: u64) -> Result<Arc<SimulationJob>, JobError> {
+        if self.jobs.len() >= self.max_jobs {
+            let oldest_finished = self.order.iter()
+                .position(|id| !self.jobs[id].is_running())
+                .ok_or(JobError::Full { max_jobs: self.max_jobs })?;
+            let id = self.order.remove(oldest_finished);
+            self.jobs.remove(&id);
+        }
+
+        let mut rng = rand::thread_rng();
+        let id = loop {
+            let candidate = random_hex(&mut rng);
+            if !self.jobs.contains_key(&candidate) {
+                break candidate;
+            }
+        };
+
+        let job = Arc::new(SimulationJob::new(id.clone(), label, total));
+        self.jobs.insert(id.clone(), job.clone());
+        self.order.push(id);
+        Ok(job)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<SimulationJob>> {
+        self.jobs.get(id).cloned()
+    }
+
+    pub fn list(&self) -> Vec<JobProgress> {
+        self.order.iter().map(|id| self.jobs[id].snapshot()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_tracks_games_and_cancellation() {
+        let mut jobs = JobRegistry::new(4);
+        let job = jobs.start("deck", 3).unwrap();
+        job.record_game(4);
+        assert_eq!(job.record_game(8), 2);
+
+        let progress = job.snapshot();
+        assert_eq!(progress.status, JobStatus::Running);
+        assert_eq!(progress.avg_turns, 6.0);
+        assert_eq!((progress.min_turns, progress.max_turns), (Some(4), Some(8)));
+
+        job.cancel();
+        job.finish(None);
+        assert_eq!(jobs.get(&progress.id).unwrap().snapshot().status, JobStatus::Cancelled);
+    }
+
+    #[test]
+    fn full_registry_drops_oldest_finished_job() {
+        let mut jobs = JobRegistry::new(2);
+        let first = jobs.start("a", 1).unwrap();
+        jobs.start("b", 1).unwrap();
+        assert_eq!(jobs.start("c", 1).err(), Some(JobError::Full { max_jobs: 2 }));
+
+        first.finish(None);
+        jobs.start("c", 1).unwrap();
+        let labels: Vec<String> = jobs.list().into_iter().map(|p| p.label).collect();
+        assert_eq!(labels, vec!["b", "c"]);
+    }
+
+    #[test]
+    fn panicking_job_is_marked_failed() {
+        let mut jobs = JobRegistry::new(1);
+        let job = jobs.start("deck", 1).unwrap();
+        job.run(|| panic!("deck ran out of cards"));
+
+        let progress = job.snapshot();
+        assert_eq!(progress.status, JobStatus::Failed);
+        assert_eq!(progress.error.as_deref(), Some("deck ran out of cards"));
+        // A failed job no longer holds a slot
+        jobs.start("next", 1).unwrap();
+    }
+}
@@ -1,6 +1,6 @@
-use axum::{routing::{get, post}, Json, Router};
+use axum::{routing::{delete, get, post}, Json, Router};
 use std::sync::{Arc, Mutex};
-use engine::{GameState, GameStep};
+use engine::{Deck, GameEvent, GameRules, GameState, GameStep, GameView, MatchupMatrix, PlayerControl, RulesError, Target, TWO_HEADED_GIANT_LIFE};
 use axum::extract::Extension;
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
@@ -12,6 +12,28 @@ use tokio::signal;
 use std::path::PathBuf;
 use socket2::{Socket, Domain, Type};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use std::convert::Infallible;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures_util::stream::{self, Stream, StreamExt};
+use tokio::sync::broadcast;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+
+mod jobs;
+mod sessions;
+mod simulations;
+use jobs::{JobError, JobProgress, JobRegistry, SimulationJob};
+use sessions::{GameRegistry, GameSession, RegistryError, SeatError};
+use simulations::{run_plan, MatchupRequest, SimulationError, SimulationPlan, SimulationRequest, SweepRequest};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+// Limits for hosted games shared by everyone using this server
+const MAX_GAMES: usize = 64;
+const MAX_PLAYERS: usize = 8;
+const IDLE_GAME_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+const IDLE_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
 
 /// Find the web directory relative to the project root
 fn find_web_dir() -> PathBuf {
@@ -82,21 +104,39 @@ fn kill_process_on_port(_port: u16) {
 #[tokio::main]
 async fn main()
 {
-    let game = Arc::new(Mutex::new(GameState::new_default()));
+    let registry: Registry = Arc::new(Mutex::new(GameRegistry::new(MAX_GAMES, IDLE_GAME_TIMEOUT)));
+    let jobs: Jobs = Arc::new(Mutex::new(JobRegistry::new(MAX_SIMULATION_JOBS)));
     let shutdown_flag = Arc::new(AtomicBool::new(false));
 
     // API routes
     let api = Router::new()
-        .route("/state", get(get_state))
-        .route("/step", post(post_step))
-        .route("/turn", post(post_turn))
-        .route("/game", post(post_game))
+        .route("/games", get(get_games).post(post_create_game))
+        .route("/games/:id", delete(delete_game))
+        .route("/games/:id/join", post(post_join_game))
+        .route("/games/:id/state", get(get_state))
+        .route("/games/:id/events", get(get_game_events))
+        .route("/games/:id/step", post(post_step))
+        .route("/games/:id/turn", post(post_turn))
+        .route("/games/:id/game", post(post_game))
+        .route("/games/:id/restart", post(post_restart))
+        .route("/games/:id/declare-attackers", post(post_declare_attackers))
+        .route("/games/:id/declare-blockers", post(post_declare_blockers))
+        .route("/games/:id/play-land", post(post_play_land))
+        .route("/games/:id/cast", post(post_cast))
+        .route("/games/:id/pass", post(post_pass))
+        .route("/games/:id/activate", post(post_activate))
+        .route("/games/:id/discard", post(post_discard))
+        .route("/games/:id/targets", post(post_targets))
+        .route("/games/:id/library", post(post_library))
+        .route("/games/:id/control", post(post_control))
+        .route("/games/:id/advance", post(post_advance))
         .route("/deck", post(post_deck))
         .route("/all", post(post_all))
-        .route("/restart", post(post_restart))
-        .route("/declare-attackers", post(post_declare_attackers))
-        .route("/declare-blockers", post(post_declare_blockers))
-        .route("/toggle-auto-play", post(post_toggle_auto_play))
+        .route("/simulations", get(get_simulations).post(post_simulation))
+        .route("/simulations/events", get(get_simulation_events))
+        .route("/simulations/:id", get(get_simulation).delete(delete_simulation))
+        .route("/simulations/:id/csv", get(get_simulation_csv))
+        .route("/matchups", post(post_matchups))
         .route("/music-list", get(get_music_list))
         .route("/shutdown", post({
             let flag = shutdown_flag.clone();
@@ -108,7 +148,9 @@ async fn main()
                 }
             }
         }))
-        .layer(Extension(game.clone()));
+        .layer(Extension(registry.clone()))
+        .layer(Extension(jobs))
+        .layer(Extension(SimulationUpdates(broadcast::channel(64).0)));
 
     // Static routes for the web/ directory (simple handlers)
     let app = Router::new()
@@ -154,6 +196,18 @@ async fn main()
     println!("Server running at http://{}", addr);
     println!("Press Ctrl+C to stop the server, or visit http://{}:3000 and click 'Stop Server'", addr.ip());
 
+    // Periodically drop games nobody has touched for a while
+    let registry_clone = registry.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(IDLE_SWEEP_INTERVAL).await;
+            let expired = registry_clone.lock().unwrap().expire_idle();
+            if expired > 0 {
+                println!("Expired {} idle game(s)", expired);
+            }
+        }
+    });
+
     // Spawn a background task to check for shutdown flag
     let shutdown_flag_clone = shutdown_flag.clone();
     tokio::spawn(async move {
@@ -184,120 +238,759 @@ async fn main()
 #[derive(Deserialize, Serialize)]
 pub struct DeclareAttackersRequest {
     pub attacking_indices: Vec<usize>,
+    // attacker index -> player it attacks; attackers left out attack the next player
+    #[serde(default)]
+    pub targets: std::collections::HashMap<usize, usize>,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct CreateGameRequest {
+    pub players: Option<usize>,
+    /// Four players in two teams of two sharing a life total
+    #[serde(default)]
+    pub two_headed_giant: bool,
+    /// Format rules such as the maximum hand size; the defaults when left out
+    #[serde(default)]
+    pub rules: Option<GameRules>,
 }
 
 #[derive(Deserialize, Serialize)]
 pub struct DeclareBlockersRequest {
-    pub blocking_map: std::collections::HashMap<usize, usize>, // blocker index -> attacker index
+    pub blocking_map: std::collections::HashMap<usize, usize>, // blocker index (defender's battlefield, then teammates') -> attacker index
 }
 
-async fn post_declare_attackers(
-    Extension(game): Extension<Arc<Mutex<GameState>>>,
-    Json(payload): Json<DeclareAttackersRequest>,
-) -> Json<GameState> {
-    let mut g = game.lock().unwrap();
-    g.attacking_creatures = payload.attacking_indices;
-    
-    // Tap all attacking creatures
-    let attacking_to_tap = g.attacking_creatures.clone();
-    if let Some(battlefield) = g.zones_mut().get_mut(&engine::Zone::Battlefield) {
-        for idx in attacking_to_tap {
-            if idx < battlefield.len() {
-                engine::tappable::set_tapped(&mut battlefield[idx], true);
+#[derive(Deserialize, Serialize)]
+pub struct HandCardRequest {
+    pub hand_index: usize,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DiscardRequest {
+    pub hand_indices: Vec<usize>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ActivateRequest {
+    pub battlefield_index: usize,
+    pub ability_index: usize,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct TargetsRequest {
+    pub targets: Vec<Target>,
+}
+
+/// The pending scry or surveil's cards to keep on top (indices into `looking_at`, in order), or
+/// the one card a search takes, if any
+#[derive(Deserialize, Serialize)]
+pub struct LibraryRequest {
+    pub chosen: Vec<usize>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ControlRequest {
+    pub player_index: usize,
+    pub control: PlayerControl,
+}
+
+/// Error body returned by API handlers as `{ "error": ..., "detail": ... }`
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: String,
+    pub detail: serde_json::Value,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        ApiError {
+            status,
+            message: message.into(),
+            detail: serde_json::Value::Null,
+        }
+    }
+}
+
+impl From<RulesError> for ApiError {
+    fn from(e: RulesError) -> Self {
+        ApiError {
+            status: StatusCode::UNPROCESSABLE_ENTITY,
+            message: e.to_string(),
+            detail: serde_json::to_value(&e).unwrap_or(serde_json::Value::Null),
+        }
+    }
+}
+
+impl From<RegistryError> for ApiError {
+    fn from(e: RegistryError) -> Self {
+        match e {
+            RegistryError::Full { max_games } => ApiError::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!("server is already hosting the maximum of {} games", max_games),
+            ),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> axum::response::Response {
+        let body = serde_json::json!({
+            "error": self.message,
+            "detail": self.detail,
+        });
+        (self.status, Json(body)).into_response()
+    }
+}
+
+type Registry = Arc<Mutex<GameRegistry>>;
+
+/// Seat token sent by the client as an `x-seat-token` header, or a `token` query
+/// parameter for clients that can't set headers (EventSource).
+pub struct SeatToken(Option<String>);
+
+#[axum::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for SeatToken {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let from_header = parts.headers.get("x-seat-token")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        let from_query = parts.uri.query().and_then(|query| {
+            query.split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(key, _)| *key == "token")
+                .map(|(_, value)| value.to_string())
+        });
+        Ok(SeatToken(from_header.or(from_query)))
+    }
+}
+
+/// Who may perform an action on a hosted game.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Access {
+    /// Anyone, including spectators without a seat
+    Anyone,
+    /// Any client holding a seat in this game
+    Seated,
+    /// Only the client whose seat has the pending decision
+    Decision,
+    /// Moving the game on: anyone seated in a hot-seat game, but once it is networked only the
+    /// seat with the pending decision, so nobody steps past someone else's choice
+    Progress,
+    /// Anyone seated, while no other client shares the game
+    HotSeat,
+    /// Only the client that created the game, which holds seat 0
+    Creator,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct JoinRequest {
+    pub seat: Option<usize>,
+}
+
+impl From<SeatError> for ApiError {
+    fn from(e: SeatError) -> Self {
+        match e {
+            SeatError::NoSuchSeat(seat) => ApiError::new(StatusCode::BAD_REQUEST, format!("no seat {}", seat)),
+            SeatError::Taken(seat) => ApiError::new(StatusCode::CONFLICT, format!("seat {} is already taken", seat)),
+            SeatError::NoFreeSeat => ApiError::new(StatusCode::CONFLICT, "every seat is taken"),
+        }
+    }
+}
+
+fn find_session(registry: &Registry, id: &str) -> Result<Arc<Mutex<GameSession>>, ApiError> {
+    registry.lock().unwrap().get(id)
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("no game with id {}", id)))
+}
+
+fn check_access(session: &GameSession, viewer: Option<usize>, access: Access) -> Result<(), ApiError> {
+    match access {
+        Access::Anyone => Ok(()),
+        Access::Seated if viewer.is_some() => Ok(()),
+        Access::Seated => Err(ApiError::new(StatusCode::FORBIDDEN, "a seat token is required for this action")),
+        Access::Decision => {
+            let deciding = session.game.deciding_player_index();
+            if session.may_act_for(viewer, deciding) {
+                Ok(())
+            } else {
+                Err(ApiError::new(StatusCode::FORBIDDEN, format!("waiting on player {} to act", deciding)))
             }
         }
+        Access::Progress if session.is_networked() => check_access(session, viewer, Access::Decision),
+        Access::HotSeat if session.is_networked() => Err(ApiError::new(
+            StatusCode::CONFLICT,
+            "this would step past other players' decisions; use step or pass instead",
+        )),
+        Access::Progress | Access::HotSeat => check_access(session, viewer, Access::Seated),
+        Access::Creator if viewer == Some(0) => Ok(()),
+        Access::Creator => Err(ApiError::new(StatusCode::FORBIDDEN, "only the game's creator may do this")),
     }
-    
-    g.step = GameStep::DeclareBlockers;
-    Json(g.clone())
 }
 
-async fn post_declare_blockers(
-    Extension(game): Extension<Arc<Mutex<GameState>>>,
-    Json(payload): Json<DeclareBlockersRequest>,
-) -> Json<GameState> {
-    let mut g = game.lock().unwrap();
-    g.blocking_map = payload.blocking_map;
-    g.step = GameStep::AssignDamage;
-    Json(g.clone())
+// Run an action against one hosted game and respond with the new state as the caller sees it
+fn with_game<F>(registry: &Registry, id: &str, token: &SeatToken, access: Access, action: F) -> Result<Json<GameView>, ApiError>
+where
+    F: FnOnce(&mut GameState) -> Result<(), ApiError>,
+{
+    let session = find_session(registry, id)?;
+    let mut session = session.lock().unwrap();
+    let viewer = token.0.as_deref().and_then(|t| session.seat_for_token(t));
+    check_access(&session, viewer, access)?;
+
+    // Only actions keep a game alive; a spectator polling it shouldn't stop it expiring
+    let mutating = access != Access::Anyone;
+    if mutating {
+        session.touch();
+    }
+    let result = action(&mut session.game);
+
+    // Between two remote humans nobody is around to press step, so run on to the next decision
+    if result.is_ok() && mutating && session.is_networked() {
+        session.game.advance_until_decision();
+    }
+
+    session.publish();
+    result?;
+    Ok(Json(session.view_for(viewer)))
 }
-async fn get_state(Extension(game): Extension<Arc<Mutex<GameState>>>) -> Json<GameState> {
-    Json(game.lock().unwrap().clone())
+
+// Start a fresh game but keep each seat's human/AI control and the teams
+fn reset_game(g: &mut GameState) {
+    let controls: Vec<PlayerControl> = g.players.iter().map(|p| p.control).collect();
+    let teams = g.teams.clone();
+    let rules = g.rules;
+    *g = GameState::new(controls.len(), &Deck::example());
+    g.rules = rules;
+    if !teams.is_empty() {
+        let _ = g.set_teams(teams, TWO_HEADED_GIANT_LIFE);
+    }
+    for (index, control) in controls.into_iter().enumerate() {
+        g.set_player_control(index, control);
+    }
+    g.set_record_events(true);
+    let players = g.players.len();
+    g.emit(GameEvent::GameStarted { players });
 }
 
-async fn post_step(Extension(game): Extension<Arc<Mutex<GameState>>>) -> Json<GameState> {
-    let mut g = game.lock().unwrap();
-    g.step();
-    Json(g.clone())
+// Claim a seat for a human client and describe it to them; everyone watching sees it change hands
+fn seat_response(id: &str, session: &mut GameSession, seat: Option<usize>) -> Result<Json<serde_json::Value>, ApiError> {
+    let (seat, token) = session.claim_seat(seat)?;
+    if session.is_networked() {
+        session.game.advance_until_decision();
+    }
+    session.publish();
+    Ok(Json(serde_json::json!({
+        "id": id,
+        "seat": seat,
+        "token": token,
+        "state": session.view_for(Some(seat)),
+    })))
 }
 
-async fn post_turn(Extension(game): Extension<Arc<Mutex<GameState>>>) -> Json<GameState> {
-    let mut g = game.lock().unwrap();
-    let start_turn = g.turns;
-    while g.turns == start_turn && g.step != GameStep::GameOver {
-        g.step();
+// The creator of a game plays seat 0; the rest start as AI seats that others may join
+async fn post_create_game(
+    Extension(registry): Extension<Registry>,
+    payload: Option<Json<CreateGameRequest>>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let request = payload.map(|Json(r)| r).unwrap_or_default();
+    let mut game = if request.two_headed_giant {
+        if request.players.is_some_and(|players| players != 4) {
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, "Two-Headed Giant takes 4 players"));
+        }
+        GameState::two_headed_giant(&[Deck::example()], &mut rand::thread_rng())
+    } else {
+        let players = request.players.unwrap_or(2);
+        if !(2..=MAX_PLAYERS).contains(&players) {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("games take 2 to {} players", MAX_PLAYERS),
+            ));
+        }
+        GameState::new(players, &Deck::example())
+    };
+    if let Some(rules) = request.rules {
+        game.rules = rules;
     }
-    Json(g.clone())
+
+    let id = registry.lock().unwrap().create(game)?;
+    let session = find_session(&registry, &id)?;
+    let mut session = session.lock().unwrap();
+    seat_response(&id, &mut session, Some(0))
 }
 
-async fn post_game(Extension(game): Extension<Arc<Mutex<GameState>>>) -> Json<GameState> {
-    let mut g = game.lock().unwrap();
-    while g.step != GameStep::GameOver {
-        g.step();
+// Take an open seat; it is played by the joining human from now on
+async fn post_join_game(
+    Path(id): Path<String>,
+    Extension(registry): Extension<Registry>,
+    payload: Option<Json<JoinRequest>>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let session = find_session(&registry, &id)?;
+    let mut session = session.lock().unwrap();
+    session.touch();
+
+    let requested = payload.and_then(|Json(p)| p.seat);
+    seat_response(&id, &mut session, requested)
+}
+
+// Forward broadcast messages as SSE events, skipping any a slow client missed
+fn sse_stream(
+    first: Option<String>,
+    receiver: broadcast::Receiver<String>,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    let first = stream::iter(first.map(|msg| Ok(Event::default().data(msg))));
+    let rest = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(msg) => return Some((Ok(Event::default().data(msg)), receiver)),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    first.chain(rest)
+}
+
+// Stream `{ events, state }` for a game as this viewer sees it, starting with the current state
+async fn get_game_events(
+    Path(id): Path<String>,
+    Extension(registry): Extension<Registry>,
+    token: SeatToken,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let session = find_session(&registry, &id)?;
+    let (viewer, snapshot, receiver) = {
+        let session = session.lock().unwrap();
+        let viewer = token.0.as_deref().and_then(|t| session.seat_for_token(t));
+        (viewer, session.update_message(&[], viewer), session.updates.subscribe())
+    };
+
+    let first = stream::once(async move { Ok(Event::default().data(snapshot)) });
+    let rest = stream::unfold((receiver, session), move |(mut receiver, session)| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(events) => {
+                    // Each subscriber gets its own redacted view of the shared game
+                    let msg = session.lock().unwrap().update_message(&events, viewer);
+                    return Some((Ok(Event::default().data(msg)), (receiver, session)));
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    Ok(Sse::new(first.chain(rest)).keep_alive(KeepAlive::default()))
+}
+
+// Stream progress of simulation jobs
+async fn get_simulation_events(
+    Extension(SimulationUpdates(updates)): Extension<SimulationUpdates>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    Sse::new(sse_stream(None, updates.subscribe())).keep_alive(KeepAlive::default())
+}
+
+// Only the games the caller holds a seat in are listed, so game IDs stay hard to come by
+async fn get_games(Extension(registry): Extension<Registry>, token: SeatToken) -> Json<serde_json::Value> {
+    let (sessions, count) = {
+        let registry = registry.lock().unwrap();
+        let sessions: Vec<_> = registry.ids().into_iter().filter_map(|id| registry.get(&id).map(|s| (id, s))).collect();
+        (sessions, registry.len())
+    };
+    let games: Vec<String> = match &token.0 {
+        Some(token) => sessions.into_iter()
+            .filter(|(_, session)| session.lock().unwrap().seat_for_token(token).is_some())
+            .map(|(id, _)| id)
+            .collect(),
+        None => Vec::new(),
+    };
+    Json(serde_json::json!({
+        "games": games,
+        "count": count,
+    }))
+}
+
+async fn delete_game(
+    Path(id): Path<String>,
+    Extension(registry): Extension<Registry>,
+    token: SeatToken,
+) -> Result<StatusCode, ApiError> {
+    {
+        let session = find_session(&registry, &id)?;
+        let session = session.lock().unwrap();
+        let viewer = token.0.as_deref().and_then(|t| session.seat_for_token(t));
+        check_access(&session, viewer, Access::Creator)?;
     }
-    Json(g.clone())
+    registry.lock().unwrap().remove(&id);
+    Ok(StatusCode::NO_CONTENT)
 }
 
-async fn post_deck(Extension(game): Extension<Arc<Mutex<GameState>>>) -> Json<serde_json::Value> {
-    // Run 10,000 games and track average turns
-    let mut total_turns = 0;
-    for _ in 0..10000 {
-        let mut g = GameState::new_default();
-        while g.step != GameStep::GameOver {
+async fn get_state(
+    Path(id): Path<String>,
+    Extension(registry): Extension<Registry>,
+    token: SeatToken,
+) -> Result<Json<GameView>, ApiError> {
+    with_game(&registry, &id, &token, Access::Anyone, |_| Ok(()))
+}
+
+async fn post_step(
+    Path(id): Path<String>,
+    Extension(registry): Extension<Registry>,
+    token: SeatToken,
+) -> Result<Json<GameView>, ApiError> {
+    with_game(&registry, &id, &token, Access::Progress, |g| {
+        g.step();
+        Ok(())
+    })
+}
+
+async fn post_turn(
+    Path(id): Path<String>,
+    Extension(registry): Extension<Registry>,
+    token: SeatToken,
+) -> Result<Json<GameView>, ApiError> {
+    with_game(&registry, &id, &token, Access::HotSeat, |g| {
+        let start_turn = g.turns;
+        while g.turns == start_turn && g.step != GameStep::GameOver {
             g.step();
         }
-        total_turns += g.turns as u64;
-    }
-    let avg_turns = total_turns as f64 / 10000.0;
-    
-    let mut g = game.lock().unwrap();
-    *g = GameState::new_default();
-    
-    serde_json::json!({
-        "avg_turns": avg_turns,
-        "total_games": 10000,
-        "state": g.clone()
+        Ok(())
     })
-    .into()
 }
 
-async fn post_all(Extension(game): Extension<Arc<Mutex<GameState>>>) -> Json<serde_json::Value> {
-    // For now, same as deck - could be extended to run multiple deck configs
-    let mut total_turns = 0;
-    for _ in 0..10000 {
-        let mut g = GameState::new_default();
+async fn post_game(
+    Path(id): Path<String>,
+    Extension(registry): Extension<Registry>,
+    token: SeatToken,
+) -> Result<Json<GameView>, ApiError> {
+    with_game(&registry, &id, &token, Access::HotSeat, |g| {
         while g.step != GameStep::GameOver {
             g.step();
         }
-        total_turns += g.turns as u64;
+        Ok(())
+    })
+}
+
+// In a networked game every seated client has to ask before it starts over; until then the
+// request is accepted but the game carries on
+async fn post_restart(
+    Path(id): Path<String>,
+    Extension(registry): Extension<Registry>,
+    token: SeatToken,
+) -> Result<(StatusCode, Json<GameView>), ApiError> {
+    let session = find_session(&registry, &id)?;
+    let mut session = session.lock().unwrap();
+    let viewer = token.0.as_deref().and_then(|t| session.seat_for_token(t));
+    check_access(&session, viewer, Access::Seated)?;
+    session.touch();
+
+    let status = match viewer.map(|seat| session.vote_restart(seat)) {
+        Some(true) => {
+            reset_game(&mut session.game);
+            if session.is_networked() {
+                session.game.advance_until_decision();
+            }
+            session.publish();
+            StatusCode::OK
+        }
+        _ => StatusCode::ACCEPTED,
+    };
+    Ok((status, Json(session.view_for(viewer))))
+}
+
+async fn post_declare_attackers(
+    Path(id): Path<String>,
+    Extension(registry): Extension<Registry>,
+    token: SeatToken,
+    Json(payload): Json<DeclareAttackersRequest>,
+) -> Result<Json<GameView>, ApiError> {
+    with_game(&registry, &id, &token, Access::Decision, |g| {
+        let defender = g.defending_player_index();
+        let attacks = payload.attacking_indices.iter()
+            .map(|&attacker| (attacker, payload.targets.get(&attacker).copied().unwrap_or(defender)))
+            .collect();
+        Ok(g.declare_attacks(attacks)?)
+    })
+}
+
+async fn post_declare_blockers(
+    Path(id): Path<String>,
+    Extension(registry): Extension<Registry>,
+    token: SeatToken,
+    Json(payload): Json<DeclareBlockersRequest>,
+) -> Result<Json<GameView>, ApiError> {
+    with_game(&registry, &id, &token, Access::Decision, |g| Ok(g.declare_blockers(payload.blocking_map)?))
+}
+
+async fn post_play_land(
+    Path(id): Path<String>,
+    Extension(registry): Extension<Registry>,
+    token: SeatToken,
+    Json(payload): Json<HandCardRequest>,
+) -> Result<Json<GameView>, ApiError> {
+    with_game(&registry, &id, &token, Access::Decision, |g| Ok(g.play_land(payload.hand_index)?))
+}
+
+async fn post_cast(
+    Path(id): Path<String>,
+    Extension(registry): Extension<Registry>,
+    token: SeatToken,
+    Json(payload): Json<HandCardRequest>,
+) -> Result<Json<GameView>, ApiError> {
+    with_game(&registry, &id, &token, Access::Decision, |g| Ok(g.cast_creature(payload.hand_index)?))
+}
+
+async fn post_pass(
+    Path(id): Path<String>,
+    Extension(registry): Extension<Registry>,
+    token: SeatToken,
+) -> Result<Json<GameView>, ApiError> {
+    with_game(&registry, &id, &token, Access::Decision, |g| Ok(g.pass()?))
+}
+
+async fn post_discard(
+    Path(id): Path<String>,
+    Extension(registry): Extension<Registry>,
+    token: SeatToken,
+    Json(payload): Json<DiscardRequest>,
+) -> Result<Json<GameView>, ApiError> {
+    with_game(&registry, &id, &token, Access::Decision, |g| Ok(g.discard_to_hand_size(payload.hand_indices)?))
+}
+
+async fn post_activate(
+    Path(id): Path<String>,
+    Extension(registry): Extension<Registry>,
+    token: SeatToken,
+    Json(payload): Json<ActivateRequest>,
+) -> Result<Json<GameView>, ApiError> {
+    with_game(&registry, &id, &token, Access::Decision, |g| Ok(g.activate_ability(payload.battlefield_index, payload.ability_index)?))
+}
+
+async fn post_targets(
+    Path(id): Path<String>,
+    Extension(registry): Extension<Registry>,
+    token: SeatToken,
+    Json(payload): Json<TargetsRequest>,
+) -> Result<Json<GameView>, ApiError> {
+    with_game(&registry, &id, &token, Access::Decision, |g| Ok(g.choose_targets(payload.targets)?))
+}
+
+async fn post_library(
+    Path(id): Path<String>,
+    Extension(registry): Extension<Registry>,
+    token: SeatToken,
+    Json(payload): Json<LibraryRequest>,
+) -> Result<Json<GameView>, ApiError> {
+    with_game(&registry, &id, &token, Access::Decision, |g| Ok(g.choose_library(payload.chosen)?))
+}
+
+async fn post_control(
+    Path(id): Path<String>,
+    Extension(registry): Extension<Registry>,
+    token: SeatToken,
+    Json(payload): Json<ControlRequest>,
+) -> Result<Json<GameView>, ApiError> {
+    {
+        // Only the seat's own client (or anyone seated, for an open seat) may hand it to the AI
+        let session = find_session(&registry, &id)?;
+        let session = session.lock().unwrap();
+        let viewer = token.0.as_deref().and_then(|t| session.seat_for_token(t));
+        if !session.may_act_for(viewer, payload.player_index) {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                format!("player {} is controlled by another client", payload.player_index),
+            ));
+        }
     }
-    let avg_turns = total_turns as f64 / 10000.0;
-    
-    let mut g = game.lock().unwrap();
-    *g = GameState::new_default();
-    
-    serde_json::json!({
-        "avg_turns": avg_turns,
-        "total_games": 10000,
-        "state": g.clone()
+
+    with_game(&registry, &id, &token, Access::Seated, |g| {
+        if payload.player_index >= g.players.len() {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("no player at index {}", payload.player_index),
+            ));
+        }
+        g.set_player_control(payload.player_index, payload.control);
+        Ok(())
     })
-    .into()
 }
 
-async fn post_restart(Extension(game): Extension<Arc<Mutex<GameState>>>) -> Json<GameState> {
-    let mut g = game.lock().unwrap();
-    *g = GameState::new_default();
-    Json(g.clone())
+// Run AI seats until a human has something to decide
+async fn post_advance(
+    Path(id): Path<String>,
+    Extension(registry): Extension<Registry>,
+    token: SeatToken,
+) -> Result<Json<GameView>, ApiError> {
+    with_game(&registry, &id, &token, Access::Progress, |g| {
+        g.advance_until_decision();
+        Ok(())
+    })
+}
+
+/// Server-wide channel for simulation progress messages.
+#[derive(Clone)]
+struct SimulationUpdates(broadcast::Sender<String>);
+
+type Jobs = Arc<Mutex<JobRegistry>>;
+
+// Games between progress messages
+const SIMULATION_PROGRESS_INTERVAL: u64 = 500;
+// Simulation jobs (running or finished) kept at once
+const MAX_SIMULATION_JOBS: usize = 32;
+
+impl From<JobError> for ApiError {
+    fn from(e: JobError) -> Self {
+        match e {
+            JobError::Full { max_jobs } => ApiError::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!("server is already tracking {} running simulations", max_jobs),
+            ),
+        }
+    }
+}
+
+impl From<SimulationError> for ApiError {
+    fn from(e: SimulationError) -> Self {
+        ApiError::new(StatusCode::BAD_REQUEST, e.to_string())
+    }
+}
+
+// Register a job for the plan and run it in the background; responds straight away with its progress
+fn start_simulation(jobs: &Jobs, updates: broadcast::Sender<String>, label: &str, plan: SimulationPlan) -> Result<(StatusCode, Json<JobProgress>), ApiError> {
+    let total = plan.games * plan.configs.len() as u64;
+    let job = jobs.lock().unwrap().start(label, total)?;
+    let snapshot = job.snapshot();
+
+    tokio::task::spawn_blocking(move || {
+        job.run(|| {
+            let results = run_plan(&plan, &job, |completed| {
+                if completed.is_multiple_of(SIMULATION_PROGRESS_INTERVAL) || completed == total {
+                    let _ = updates.send(serde_json::to_string(&job.snapshot()).unwrap_or_default());
+                }
+            });
+
+            // Fastest kill wins; ties go to the better win rate
+            let best = results.iter().min_by(|a, b| {
+                a.avg_turns.total_cmp(&b.avg_turns).then(b.win_rate.total_cmp(&a.win_rate))
+            }).cloned();
+            let progress = job.snapshot();
+            Some(serde_json::json!({
+                "avg_turns": progress.avg_turns,
+                "total_games": progress.completed,
+                "seed": plan.seed,
+                "opponent": plan.opponent,
+                "best": best,
+                "results": results,
+            }))
+        });
+        let _ = updates.send(serde_json::to_string(&job.snapshot()).unwrap_or_default());
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(snapshot)))
+}
+
+fn find_job(jobs: &Jobs, id: &str) -> Result<Arc<SimulationJob>, ApiError> {
+    jobs.lock().unwrap().get(id)
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("no simulation with id {}", id)))
+}
+
+async fn post_simulation(
+    Extension(jobs): Extension<Jobs>,
+    Extension(SimulationUpdates(updates)): Extension<SimulationUpdates>,
+    payload: Option<Json<SimulationRequest>>,
+) -> Result<(StatusCode, Json<JobProgress>), ApiError> {
+    let request = payload.map(|Json(r)| r).unwrap_or_default();
+    let label = request.label.clone().unwrap_or_else(|| String::from("simulation"));
+    start_simulation(&jobs, updates, &label, request.plan()?)
+}
+
+async fn get_simulations(Extension(jobs): Extension<Jobs>) -> Json<Vec<JobProgress>> {
+    Json(jobs.lock().unwrap().list())
+}
+
+async fn get_simulation(
+    Path(id): Path<String>,
+    Extension(jobs): Extension<Jobs>,
+) -> Result<Json<JobProgress>, ApiError> {
+    Ok(Json(find_job(&jobs, &id)?.snapshot()))
+}
+
+// Ask a running job to stop; it finishes the game in progress and keeps its partial statistics
+async fn delete_simulation(
+    Path(id): Path<String>,
+    Extension(jobs): Extension<Jobs>,
+) -> Result<Json<JobProgress>, ApiError> {
+    let job = find_job(&jobs, &id)?;
+    job.cancel();
+    Ok(Json(job.snapshot()))
+}
+
+// Play every deck in a gauntlet against every other; the result holds the win-rate matrix
+async fn post_matchups(
+    Extension(jobs): Extension<Jobs>,
+    Extension(SimulationUpdates(updates)): Extension<SimulationUpdates>,
+    Json(request): Json<MatchupRequest>,
+) -> Result<(StatusCode, Json<JobProgress>), ApiError> {
+    let plan = request.plan()?;
+    let total = plan.games * plan.pairings();
+    let job = jobs.lock().unwrap().start("matchups", total)?;
+    let snapshot = job.snapshot();
+
+    tokio::task::spawn_blocking(move || {
+        job.run(|| {
+            let mut rng = StdRng::seed_from_u64(plan.seed);
+            let matrix = engine::run_matchups(&plan.decks, plan.games, &mut rng, |game| {
+                let completed = job.record_game(game.turns);
+                if completed.is_multiple_of(SIMULATION_PROGRESS_INTERVAL) || completed == total {
+                    let _ = updates.send(serde_json::to_string(&job.snapshot()).unwrap_or_default());
+                }
+                !job.is_cancelled()
+            });
+
+            Some(serde_json::json!({
+                "seed": plan.seed,
+                "games_per_pairing": plan.games,
+                "matrix": matrix,
+            }))
+        });
+        let _ = updates.send(serde_json::to_string(&job.snapshot()).unwrap_or_default());
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(snapshot)))
+}
+
+// A finished matchup job's matrix as CSV, one row per pairing
+async fn get_simulation_csv(
+    Path(id): Path<String>,
+    Extension(jobs): Extension<Jobs>,
+) -> Result<impl IntoResponse, ApiError> {
+    let progress = find_job(&jobs, &id)?.snapshot();
+    let matrix: MatchupMatrix = progress.result
+        .and_then(|result| serde_json::from_value(result["matrix"].clone()).ok())
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("simulation {} has no matchup matrix", id)))?;
+
+    Ok((
+        [(axum::http::header::CONTENT_TYPE, "text/csv"),
+         (axum::http::header::CONTENT_DISPOSITION, "attachment; filename=\"matchups.csv\"")],
+        matrix.to_csv(),
+    ))
+}
+
+// One deck (the example deck unless a decklist is given) against an opponent
+async fn post_deck(
+    Extension(jobs): Extension<Jobs>,
+    Extension(SimulationUpdates(updates)): Extension<SimulationUpdates>,
+    payload: Option<Json<SimulationRequest>>,
+) -> Result<(StatusCode, Json<JobProgress>), ApiError> {
+    let request = payload.map(|Json(r)| r).unwrap_or_default();
+    start_simulation(&jobs, updates, "deck", request.plan()?)
+}
+
+// Sweep the land/nonland ratio and report every configuration tried
+async fn post_all(
+    Extension(jobs): Extension<Jobs>,
+    Extension(SimulationUpdates(updates)): Extension<SimulationUpdates>,
+    payload: Option<Json<SweepRequest>>,
+) -> Result<(StatusCode, Json<JobProgress>), ApiError> {
+    let request = payload.map(|Json(r)| r).unwrap_or_default();
+    start_simulation(&jobs, updates, "all", request.plan()?)
 }
 
 async fn get_music_list() -> Json<serde_json::Value> {
@@ -327,14 +1020,6 @@ async fn get_music_list() -> Json<serde_json::Value> {
     }))
 }
 
-async fn post_toggle_auto_play(
-    Extension(game): Extension<Arc<Mutex<GameState>>>,
-) -> Json<GameState> {
-    let mut g = game.lock().unwrap();
-    g.auto_play = !g.auto_play;
-    Json(g.clone())
-}
-
 async fn index() -> impl IntoResponse {
     match tokio::fs::read_to_string(web_path("web/index.html")).await {
         Ok(s) => ([("content-type", "text/html; charset=utf-8")], s).into_response(),
@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use rand::Rng;
+use tokio::sync::broadcast;
+use engine::{GameEvent, GameState, GameView, PlayerControl};
+
+pub type GameId = String;
+
+// Updates a slow subscriber may fall behind by before it starts skipping
+const UPDATE_CHANNEL_CAPACITY: usize = 64;
+
+pub fn random_hex(rng: &mut impl Rng) -> String {
+    format!("{:016x}{:016x}", rng.r#gen::<u64>(), rng.r#gen::<u64>())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SeatError {
+    NoSuchSeat(usize),
+    Taken(usize),
+    NoFreeSeat,
+}
+
+/// A hosted game plus the bookkeeping needed to expire it, seat its players and push updates
+/// to watchers.
+pub struct GameSession {
+    pub game: GameState,
+    pub last_active: Instant,
+    pub updates: broadcast::Sender<Vec<GameEvent>>,
+    // Secret token of whoever claimed each seat; None while the seat is open
+    seat_tokens: Vec<Option<String>>,
+    // Claimed seats that have asked to restart since the last restart
+    restart_votes: Vec<bool>,
+}
+
+impl GameSession {
+    pub fn new(mut game: GameState) -> Self {
+        game.set_record_events(true);
+        let players = game.players.len();
+        game.emit(GameEvent::GameStarted { players });
+
+        let (updates, _) = broadcast::channel(UPDATE_CHANNEL_CAPACITY);
+        GameSession { game, last_active: Instant::now(), updates, seat_tokens: vec![None; players], restart_votes: vec![false; players] }
+    }
+
+    pub fn touch(&mut self) {
+        self.last_active = Instant::now();
+    }
+
+    /// Claim `seat` (or the first open one) for a human and return it with the token that proves it.
+    pub fn claim_seat(&mut self, seat: Option<usize>) -> Result<(usize, String), SeatError> {
+        let seat = match seat {
+            Some(seat) if seat >= self.seat_tokens.len() => return Err(SeatError::NoSuchSeat(seat)),
+            Some(seat) if self.seat_tokens[seat].is_some() => return Err(SeatError::Taken(seat)),
+            Some(seat) => seat,
+            None => self.seat_tokens.iter().position(|t| t.is_none()).ok_or(SeatError::NoFreeSeat)?,
+        };
+
+        let token = random_hex(&mut rand::thread_rng());
+        self.seat_tokens[seat] = Some(token.clone());
+        self.game.set_player_control(seat, PlayerControl::Human);
+        Ok((seat, token))
+    }
+
+    pub fn seat_for_token(&self, token: &str) -> Option<usize> {
+        self.seat_tokens.iter().position(|t| t.as_deref() == Some(token))
+    }
+
+    /// Two or more seats are claimed by different clients.
+    pub fn is_networked(&self) -> bool {
+        self.seat_tokens.iter().filter(|t| t.is_some()).count() > 1
+    }
+
+    /// Record that `seat` wants to start over. True once every claimed seat has asked, which
+    /// clears the votes for next time.
+    pub fn vote_restart(&mut self, seat: usize) -> bool {
+        self.restart_votes[seat] = true;
+        let agreed = self.seat_tokens.iter().zip(&self.restart_votes).all(|(token, &voted)| token.is_none() || voted);
+        if agreed {
+            self.restart_votes.fill(false);
+        }
+        agreed
+    }
+
+    /// A seated client acts for its own seat and for any open seat (hot-seat play).
+    pub fn may_act_for(&self, viewer: Option<usize>, seat: usize) -> bool {
+        match viewer {
+            Some(viewer) => viewer == seat || self.seat_tokens.get(seat).is_some_and(|t| t.is_none()),
+            None => false,
+        }
+    }
+
+    // Seats whose hands `viewer` may see: the ones they may act for
+    fn visible_hands(&self, viewer: Option<usize>) -> Vec<usize> {
+        (0..self.game.players.len())
+            .filter(|&seat| self.may_act_for(viewer, seat))
+            .collect()
+    }
+
+    /// The game as `viewer` may see it: the hands of the seats they may act for.
+    pub fn view_for(&self, viewer: Option<usize>) -> GameView {
+        self.game.view_for(&self.visible_hands(viewer))
+    }
+
+    /// JSON message carrying the given events and the state as `viewer` sees them.
+    pub fn update_message(&self, events: &[GameEvent], viewer: Option<usize>) -> String {
+        serde_json::json!({
+            "events": engine::events_for(events, &self.visible_hands(viewer)),
+            "state": self.view_for(viewer),
+        })
+        .to_string()
+    }
+
+    /// Send everything that happened since the last publish to subscribers, if anything did.
+    pub fn publish(&mut self) {
+        let events = self.game.drain_events();
+        if !events.is_empty() {
+            // No receivers is fine; nobody is watching this game
+            let _ = self.updates.send(events);
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RegistryError {
+    Full { max_games: usize },
+}
+
+/// All games hosted by this server, keyed by game ID. Each game has its own lock so a long
+/// action in one game doesn't block the others.
+pub struct GameRegistry {
+    sessions: HashMap<GameId, Arc<Mutex<GameSession>>>,
+    max_games: usize,
+    idle_timeout: Duration,
+}
+
+impl GameRegistry {
+    pub fn new(max_games: usize, idle_timeout: Duration) -> Self {
+        GameRegistry {
+            sessions: HashMap::new(),
+            max_games,
+            idle_timeout,
+        }
+    }
+
+    pub fn create(&mut self, game: GameState) -> Result<GameId, RegistryError> {
+        if self.sessions.len() >= self.max_games {
+            return Err(RegistryError::Full { max_games: self.max_games });
+        }
+
+        // Random IDs so one teammate can't stumble into another's game by counting
+        let mut rng = rand::thread_rng();
+        let id = loop {
+            let candidate = random_hex(&mut rng);
+            if !self.sessions.contains_key(&candidate) {
+                break candidate;
+            }
+        };
+
+        let session = GameSession::new(game);
+        self.sessions.insert(id.clone(), Arc::new(Mutex::new(session)));
+        Ok(id)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<Mutex<GameSession>>> {
+        self.sessions.get(id).cloned()
+    }
+
+    pub fn remove(&mut self, id: &str) -> bool {
+        self.sessions.remove(id).is_some()
+    }
+
+    pub fn ids(&self) -> Vec<GameId> {
+        self.sessions.keys().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Drop games nobody has touched within the idle timeout. Returns how many were removed.
+    pub fn expire_idle(&mut self) -> usize {
+        let timeout = self.idle_timeout;
+        let before = self.sessions.len();
+        self.sessions.retain(|_, session| {
+            // A game that is locked right now is in use
+            match session.try_lock() {
+                Ok(s) => s.last_active.elapsed() < timeout,
+                Err(_) => true,
+            }
+        });
+        before - self.sessions.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create_respects_cap_and_remove_frees_slot() {
+        let mut registry = GameRegistry::new(2, Duration::from_secs(60));
+        let a = registry.create(GameState::new_default()).unwrap();
+        registry.create(GameState::new_default()).unwrap();
+        assert_eq!(registry.create(GameState::new_default()), Err(RegistryError::Full { max_games: 2 }));
+
+        assert!(registry.remove(&a));
+        assert!(registry.get(&a).is_none());
+        assert!(registry.create(GameState::new_default()).is_ok());
+    }
+
+    #[test]
+    fn seats_are_claimed_once_and_identified_by_token() {
+        let mut session = GameSession::new(GameState::new_default());
+        let (seat0, token0) = session.claim_seat(None).unwrap();
+        assert_eq!(seat0, 0);
+        assert!(!session.is_networked());
+        assert!(session.may_act_for(Some(0), 1), "open seats can be played hot-seat");
+
+        assert_eq!(session.claim_seat(Some(0)), Err(SeatError::Taken(0)));
+        let (seat1, token1) = session.claim_seat(None).unwrap();
+        assert_eq!(seat1, 1);
+        assert_eq!(session.claim_seat(None), Err(SeatError::NoFreeSeat));
+        assert!(session.game.players.iter().all(|p| p.control == PlayerControl::Human));
+        assert!(session.game.drain_events().contains(&GameEvent::ControlChanged { player: 1, control: PlayerControl::Human }));
+
+        assert!(session.is_networked());
+        assert_eq!(session.seat_for_token(&token0), Some(0));
+        assert_eq!(session.seat_for_token(&token1), Some(1));
+        assert_eq!(session.seat_for_token("nope"), None);
+        assert!(!session.may_act_for(Some(0), 1));
+        assert!(!session.may_act_for(None, 0));
+
+        let view = session.view_for(Some(1));
+        assert!(view.state.players[0].zones[&engine::Zone::Hand].is_empty());
+        assert_eq!(view.state.players[1].zones[&engine::Zone::Hand].len(), 7);
+    }
+
+    #[test]
+    fn networked_games_restart_once_every_seat_agrees() {
+        let mut session = GameSession::new(GameState::new(3, &engine::Deck::example()));
+        let (seat0, _) = session.claim_seat(None).unwrap();
+        assert!(session.vote_restart(seat0), "a lone client restarts at once");
+
+        let (seat1, _) = session.claim_seat(None).unwrap();
+        assert!(!session.vote_restart(seat0));
+        assert!(!session.vote_restart(seat0));
+        assert!(session.vote_restart(seat1), "the open third seat has no say");
+        assert!(!session.vote_restart(seat1), "votes are cleared by the restart");
+    }
+
+    #[test]
+    fn idle_games_expire() {
+        let mut registry = GameRegistry::new(4, Duration::from_millis(0));
+        let id = registry.create(GameState::new_default()).unwrap();
+        assert_eq!(registry.expire_idle(), 1);
+        assert!(registry.get(&id).is_none());
+    }
+}
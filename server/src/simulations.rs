@@ -0,0 +1,379 @@
+use std::collections::BTreeMap;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use engine::{CardFilter, Deck, FilterError, UnknownCard};
+
+use crate::jobs::SimulationJob;
+
+pub const DEFAULT_SIMULATION_GAMES: u64 = 10000;
+pub const DEFAULT_MATCHUP_GAMES: u64 = 1000;
+// Games one job may play in total, across every configuration
+pub const MAX_SIMULATION_GAMES: u64 = 1_000_000;
+// Cards one deck may hold, checked before the deck is built
+pub const MAX_DECK_SIZE: u64 = 10_000;
+// Land counts tried either side of the base deck when a sweep gives no range
+const DEFAULT_SWEEP_SPREAD: u32 = 4;
+
+/// Card name -> number of copies.
+pub type DeckList = BTreeMap<String, u32>;
+
+/// One deck against one opponent, as accepted by `/deck` and `/simulations`.
+#[derive(Deserialize, Serialize, Default)]
+pub struct SimulationRequest {
+    pub label: Option<String>,
+    pub games: Option<u64>,
+    pub seed: Option<u64>,
+    /// Full decklist; defaults to the example deck
+    pub deck: Option<DeckList>,
+    /// Copies to set on top of `deck`; zero removes a card
+    pub counts: Option<DeckList>,
+    /// Deck for the other seat; defaults to a mirror match
+    pub opponent: Option<DeckList>,
+    /// Card filter, e.g. "creature yours battlefield"; results report how many cards match it
+    /// for the tested deck's seat when each game ends
+    pub count: Option<String>,
+}
+
+/// A land/nonland ratio sweep, as accepted by `/all`. `games` is per configuration.
+#[derive(Deserialize, Serialize, Default)]
+pub struct SweepRequest {
+    #[serde(flatten)]
+    pub base: SimulationRequest,
+    pub land: Option<String>,
+    pub nonland: Option<String>,
+    pub min_lands: Option<u32>,
+    pub max_lands: Option<u32>,
+    pub step: Option<u32>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SimulationError {
+    UnknownCard(String),
+    EmptyDeck,
+    NoGames,
+    TooManyGames { requested: u64, max: u64 },
+    DeckTooLarge { cards: u64, max: u64 },
+    BadSweep(String),
+    BadGauntlet(String),
+    BadFilter(String),
+}
+
+impl std::fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SimulationError::UnknownCard(name) => write!(f, "unknown card: {}", name),
+            SimulationError::EmptyDeck => write!(f, "deck has no cards"),
+            SimulationError::NoGames => write!(f, "at least one game is needed"),
+            SimulationError::TooManyGames { requested, max } => write!(f, "{} games requested, at most {} allowed", requested, max),
+            SimulationError::DeckTooLarge { cards, max } => write!(f, "{} cards in a deck, at most {} allowed", cards, max),
+            SimulationError::BadSweep(reason) => write!(f, "bad sweep: {}", reason),
+            SimulationError::BadGauntlet(reason) => write!(f, "bad gauntlet: {}", reason),
+            SimulationError::BadFilter(reason) => write!(f, "bad count filter: {}", reason),
+        }
+    }
+}
+
+impl From<UnknownCard> for SimulationError {
+    fn from(e: UnknownCard) -> Self {
+        SimulationError::UnknownCard(e.0)
+    }
+}
+
+impl From<FilterError> for SimulationError {
+    fn from(e: FilterError) -> Self {
+        SimulationError::BadFilter(e.0)
+    }
+}
+
+/// Everything a job needs to run: the decks to test, one opponent and how many games each.
+pub struct SimulationPlan {
+    pub configs: Vec<DeckList>,
+    pub opponent: Option<DeckList>,
+    pub games: u64,
+    pub seed: u64,
+    pub count: Option<CardFilter>,
+}
+
+// Summed as u64 so no list of u32 counts can overflow
+fn check_deck_size(list: &DeckList) -> Result<(), SimulationError> {
+    let cards: u64 = list.values().map(|&copies| u64::from(copies)).sum();
+    if cards > MAX_DECK_SIZE {
+        return Err(SimulationError::DeckTooLarge { cards, max: MAX_DECK_SIZE });
+    }
+    Ok(())
+}
+
+fn build_deck(list: &DeckList) -> Result<Deck, SimulationError> {
+    check_deck_size(list)?;
+    let deck = Deck::from_counts(list)?;
+    if deck.cards.is_empty() {
+        return Err(SimulationError::EmptyDeck);
+    }
+    Ok(deck)
+}
+
+impl SimulationRequest {
+    fn deck_list(&self) -> DeckList {
+        let mut list = self.deck.clone().unwrap_or_else(|| Deck::example().counts());
+        for (name, &copies) in self.counts.iter().flatten() {
+            if copies == 0 {
+                list.remove(name);
+            } else {
+                list.insert(name.clone(), copies);
+            }
+        }
+        list
+    }
+
+    fn plan_with(&self, configs: Vec<DeckList>) -> Result<SimulationPlan, SimulationError> {
+        let games = self.games.unwrap_or(DEFAULT_SIMULATION_GAMES);
+        if games == 0 {
+            return Err(SimulationError::NoGames);
+        }
+        let requested = games.saturating_mul(configs.len() as u64);
+        if requested > MAX_SIMULATION_GAMES {
+            return Err(SimulationError::TooManyGames { requested, max: MAX_SIMULATION_GAMES });
+        }
+
+        // Validate every deck now so a typo fails the request rather than the job
+        for list in configs.iter().chain(self.opponent.iter()) {
+            build_deck(list)?;
+        }
+
+        let count = self.count.as_deref().map(str::parse::<CardFilter>).transpose()?;
+        Ok(SimulationPlan {
+            configs,
+            opponent: self.opponent.clone(),
+            games,
+            count,
+            // Always report a seed so any run can be repeated
+            seed: self.seed.unwrap_or_else(|| rand::thread_rng().r#gen()),
+        })
+    }
+
+    pub fn plan(&self) -> Result<SimulationPlan, SimulationError> {
+        self.plan_with(vec![self.deck_list()])
+    }
+}
+
+impl SweepRequest {
+    /// One configuration per land count, keeping the deck size and every other card fixed.
+    pub fn plan(&self) -> Result<SimulationPlan, SimulationError> {
+        let land = self.land.clone().unwrap_or_else(|| String::from("Forest"));
+        let nonland = self.nonland.clone().unwrap_or_else(|| String::from("Grizzly Bears"));
+        if land == nonland {
+            return Err(SimulationError::BadSweep(String::from("land and nonland must differ")));
+        }
+
+        let base = self.base.deck_list();
+        // Bounds the number of configurations too, since each land count fits in the deck
+        check_deck_size(&base)?;
+        let too_many = || SimulationError::BadSweep(String::from("too many lands"));
+        let base_lands = base.get(&land).copied().unwrap_or(0);
+        let flexible = base_lands.checked_add(base.get(&nonland).copied().unwrap_or(0)).ok_or_else(too_many)?;
+        let min_lands = self.min_lands.unwrap_or(base_lands.saturating_sub(DEFAULT_SWEEP_SPREAD));
+        let max_lands = match self.max_lands {
+            Some(max_lands) => max_lands,
+            None => base_lands.checked_add(DEFAULT_SWEEP_SPREAD).ok_or_else(too_many)?,
+        }.min(flexible);
+        let step = self.step.unwrap_or(1);
+        if step == 0 || min_lands > max_lands {
+            return Err(SimulationError::BadSweep(format!(
+                "no land counts between {} and {} in steps of {}", min_lands, max_lands, step
+            )));
+        }
+
+        let configs = (min_lands..=max_lands).step_by(step as usize).map(|lands| {
+            let mut list = base.clone();
+            list.insert(land.clone(), lands);
+            list.insert(nonland.clone(), flexible - lands);
+            list.retain(|_, copies| *copies > 0);
+            list
+        }).collect();
+
+        self.base.plan_with(configs)
+    }
+}
+
+/// A named deck in a gauntlet.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct NamedDeck {
+    pub name: String,
+    pub deck: DeckList,
+}
+
+/// Every deck against every other, as accepted by `/matchups`. `games` is per pairing.
+#[derive(Deserialize, Serialize, Default)]
+pub struct MatchupRequest {
+    pub decks: Vec<NamedDeck>,
+    pub games: Option<u64>,
+    pub seed: Option<u64>,
+}
+
+/// A validated gauntlet, ready to hand to `engine::run_matchups`.
+pub struct MatchupPlan {
+    pub decks: Vec<(String, Deck)>,
+    pub games: u64,
+    pub seed: u64,
+}
+
+impl MatchupPlan {
+    pub fn pairings(&self) -> u64 {
+        let n = self.decks.len() as u64;
+        n * (n - 1) / 2
+    }
+}
+
+impl MatchupRequest {
+    pub fn plan(&self) -> Result<MatchupPlan, SimulationError> {
+        if self.decks.len() < 2 {
+            return Err(SimulationError::BadGauntlet(String::from("at least two decks are needed")));
+        }
+        for (i, entry) in self.decks.iter().enumerate() {
+            if self.decks[..i].iter().any(|other| other.name == entry.name) {
+                return Err(SimulationError::BadGauntlet(format!("deck name {} is used twice", entry.name)));
+            }
+        }
+
+        let decks = self.decks.iter()
+            .map(|entry| Ok((entry.name.clone(), build_deck(&entry.deck)?)))
+            .collect::<Result<Vec<_>, SimulationError>>()?;
+
+        let games = self.games.unwrap_or(DEFAULT_MATCHUP_GAMES);
+        if games == 0 {
+            return Err(SimulationError::NoGames);
+        }
+        let plan = MatchupPlan {
+            decks,
+            games,
+            seed: self.seed.unwrap_or_else(|| rand::thread_rng().r#gen()),
+        };
+        let requested = games.saturating_mul(plan.pairings());
+        if requested > MAX_SIMULATION_GAMES {
+            return Err(SimulationError::TooManyGames { requested, max: MAX_SIMULATION_GAMES });
+        }
+        Ok(plan)
+    }
+}
+
+/// How one configuration did against the opponent.
+#[derive(Clone, Debug, Serialize)]
+pub struct ConfigResult {
+    pub deck: DeckList,
+    pub games: u64,
+    pub avg_turns: f64,
+    pub win_rate: f64,
+    /// Cards the tested deck discarded to hand size, per game
+    pub avg_discards: f64,
+    /// Cards matching the request's `count` filter at the end of each game, if it gave one
+    pub avg_matching: Option<f64>,
+}
+
+/// Play out a plan, recording every game on the job. The tested deck goes first in every other
+/// game. Stops early if the job is cancelled, so
+/// the last configuration may have fewer games than asked for.
+pub fn run_plan(plan: &SimulationPlan, job: &SimulationJob, mut on_progress: impl FnMut(u64)) -> Vec<ConfigResult> {
+    let mut rng = StdRng::seed_from_u64(plan.seed);
+    let opponent = plan.opponent.as_ref().map(|list| build_deck(list).unwrap_or(Deck { cards: Vec::new() }));
+    let mut results = Vec::new();
+
+    for list in &plan.configs {
+        let deck = build_deck(list).unwrap_or(Deck { cards: Vec::new() });
+        let other = opponent.clone().unwrap_or_else(|| deck.clone());
+        // The tested deck swaps seats each game so going first doesn't skew its results
+        let seatings = [vec![deck.clone(), other.clone()], vec![other, deck]];
+
+        let mut games = 0;
+        let mut total_turns = 0;
+        let mut wins = 0;
+        let mut discards = 0;
+        let mut matching = 0;
+        while games < plan.games && !job.is_cancelled() {
+            let seat = (games % 2) as usize;
+            let game = engine::play_game(&seatings[seat], &mut rng);
+            games += 1;
+            total_turns += game.turns as u64;
+            discards += game.players[seat].cards_discarded as u64;
+            if let Some(filter) = &plan.count {
+                matching += game.count_matching(filter, seat) as u64;
+            }
+            if game.winner() == Some(seat) {
+                wins += 1;
+            }
+            on_progress(job.record_game(game.turns));
+        }
+
+        if games > 0 {
+            results.push(ConfigResult {
+                deck: list.clone(),
+                games,
+                avg_turns: total_turns as f64 / games as f64,
+                win_rate: wins as f64 / games as f64,
+                avg_discards: discards as f64 / games as f64,
+                avg_matching: plan.count.as_ref().map(|_| matching as f64 / games as f64),
+            });
+        }
+        if job.is_cancelled() {
+            break;
+        }
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sweep_keeps_deck_size_and_rejects_unknown_cards() {
+        let request = SweepRequest { min_lands: Some(27), max_lands: Some(31), step: Some(2), ..Default::default() };
+        let plan = request.plan().unwrap();
+        let lands: Vec<u32> = plan.configs.iter().map(|c| c["Forest"]).collect();
+        assert_eq!(lands, vec![27, 29, 31]);
+        assert!(plan.configs.iter().all(|c| c.values().sum::<u32>() == 60));
+
+        let mut counts = DeckList::new();
+        counts.insert(String::from("Black Lotus"), 4);
+        let request = SimulationRequest { counts: Some(counts), ..Default::default() };
+        assert_eq!(request.plan().err(), Some(SimulationError::UnknownCard(String::from("Black Lotus"))));
+
+        let request = SimulationRequest { count: Some(String::from("creature power>>2")), ..Default::default() };
+        assert!(matches!(request.plan().err(), Some(SimulationError::BadFilter(_))));
+    }
+
+    #[test]
+    fn oversized_decks_are_refused_before_building() {
+        let request = SimulationRequest {
+            deck: Some(DeckList::from([(String::from("Forest"), u32::MAX), (String::from("Grizzly Bears"), u32::MAX)])),
+            ..Default::default()
+        };
+        let cards = 2 * u64::from(u32::MAX);
+        assert_eq!(request.plan().err(), Some(SimulationError::DeckTooLarge { cards, max: MAX_DECK_SIZE }));
+
+        let sweep = SweepRequest { base: request, ..Default::default() };
+        assert_eq!(sweep.plan().err(), Some(SimulationError::DeckTooLarge { cards, max: MAX_DECK_SIZE }));
+    }
+
+    #[test]
+    fn tested_deck_alternates_seats() {
+        // Nobody can attack with only lands, so whoever draws first decks out first
+        let request = SimulationRequest {
+            deck: Some(DeckList::from([(String::from("Forest"), 20)])),
+            games: Some(4),
+            seed: Some(1),
+            ..Default::default()
+        };
+        let job = crate::jobs::JobRegistry::new(1).start("lands", 4).unwrap();
+        let results = run_plan(&request.plan().unwrap(), &job, |_| {});
+        assert_eq!(results[0].win_rate, 0.5);
+    }
+
+    #[test]
+    fn gauntlet_needs_distinct_names() {
+        let entry = NamedDeck { name: String::from("Bears"), deck: Deck::example().counts() };
+        let request = MatchupRequest { decks: vec![entry.clone(), entry], ..Default::default() };
+        assert!(matches!(request.plan().err(), Some(SimulationError::BadGauntlet(_))));
+    }
+}
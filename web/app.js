@@ -1,9 +1,17 @@
 const API_PREFIX = '/api';
+// Each tab hosts its own game on the server; the ID survives reloads via sessionStorage
+let gameId = sessionStorage.getItem('gameId');
+// Proves which seat this tab holds; other players' hands are hidden from it
+let seatToken = sessionStorage.getItem('seatToken');
 let simulationResults = null;
 
 // Combat selection state
 let selectedAttackers = new Set();
 let selectedBlockers = new Map(); // blocker index -> attacker index
+// Opponent the selected attackers go after in games of three or more; null attacks the next player
+let attackTarget = null;
+// Cards picked so far for a pending scry or surveil, in the order they go back on top
+let libraryPicks = [];
 let currentGameState = null;
 
 function setMusicVolume(value) {
@@ -14,12 +22,95 @@ function setMusicVolume(value) {
     }
 }
 
+function gameUrl(path)
+{
+    return `${API_PREFIX}/games/${gameId}/${path}`;
+}
+
+function showInviteLink()
+{
+    const link = document.getElementById('inviteLink');
+    if (link && gameId)
+    {
+        link.textContent = `${window.location.origin}${window.location.pathname}?game=${gameId}`;
+    }
+}
+
+// fetch() against this tab's game, identifying our seat
+function gameFetch(path, options = {})
+{
+    const headers = Object.assign({}, options.headers || {});
+    if (seatToken)
+    {
+        headers["X-Seat-Token"] = seatToken;
+    }
+    return fetch(gameUrl(path), Object.assign({}, options, { headers }));
+}
+
+function rememberSeat(body)
+{
+    gameId = body.id;
+    seatToken = body.token;
+    sessionStorage.setItem('gameId', gameId);
+    sessionStorage.setItem('seatToken', seatToken);
+    showInviteLink();
+    if (gameEvents)
+    {
+        subscribeToGame();
+    }
+    return body.state;
+}
+
+async function createGame()
+{
+    const res = await fetch(`${API_PREFIX}/games`, { method: "POST" });
+    if (!res.ok)
+    {
+        await reportApiError(res);
+        return null;
+    }
+    return rememberSeat(await res.json());
+}
+
+// Take the next open seat in someone else's game (shared as ?game=<id>)
+async function joinGame(id)
+{
+    const res = await fetch(`${API_PREFIX}/games/${id}/join`, {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        body: JSON.stringify({})
+    });
+    if (!res.ok)
+    {
+        await reportApiError(res);
+        return null;
+    }
+    return rememberSeat(await res.json());
+}
+
 async function fetchState()
 {
     try 
     {
-        const res = await fetch(`${API_PREFIX}/state`);
-        return res.json();
+        const invite = new URLSearchParams(window.location.search).get('game');
+        if (invite && invite !== gameId)
+        {
+            const joined = await joinGame(invite);
+            if (joined)
+            {
+                return joined;
+            }
+        }
+        if (gameId)
+        {
+            const res = await gameFetch('state');
+            if (res.ok)
+            {
+                return res.json();
+            }
+        }
+        // No game yet, or the server expired it
+        return await createGame();
     } 
     catch (e) 
     {
@@ -37,6 +128,9 @@ async function doStep(command)
             case 'step':
                 endpoint = '/step';
                 break;
+            case 'advance':
+                endpoint = '/advance';
+                break;
             case 'turn':
                 endpoint = '/turn';
                 break;
@@ -44,26 +138,18 @@ async function doStep(command)
                 endpoint = '/game';
                 break;
             case 'deck':
-                endpoint = '/deck';
-                simulationResults = await fetch(`${API_PREFIX}${endpoint}`, { method: "POST" }).then(r => r.json());
-                // After deck simulation, restart the game
-                await fetch(`${API_PREFIX}/restart`, { method: "POST" });
-                updateDisplay(simulationResults.state);
-                updateDeckInfo();
-                return;
             case 'all':
-                endpoint = '/all';
-                simulationResults = await fetch(`${API_PREFIX}${endpoint}`, { method: "POST" }).then(r => r.json());
-                // After all simulation, restart the game
-                await fetch(`${API_PREFIX}/restart`, { method: "POST" });
-                updateDisplay(simulationResults.state);
-                updateDeckInfo();
+                await runSimulationJob(`/${command}`);
                 return;
             default:
                 return;
         }
         
-        const response = await fetch(`${API_PREFIX}${endpoint}`, { method: "POST" });
+        const response = await gameFetch(endpoint.slice(1), { method: "POST" });
+        if (!response.ok) {
+            await reportApiError(response);
+            return;
+        }
         const newState = await response.json();
         updateDisplay(newState);
     } 
@@ -73,11 +159,48 @@ async function doStep(command)
     }
 }
 
+// Simulations run as server-side jobs; poll until ours is done, then restart the game
+let currentJobId = null;
+
+async function runSimulationJob(endpoint)
+{
+    const response = await fetch(`${API_PREFIX}${endpoint}`, { method: "POST" });
+    if (!response.ok) {
+        await reportApiError(response);
+        return;
+    }
+    let job = await response.json();
+    currentJobId = job.id;
+
+    while (job.status === "Running")
+    {
+        await new Promise(resolve => setTimeout(resolve, 500));
+        job = await fetch(`${API_PREFIX}/simulations/${job.id}`).then(r => r.json());
+    }
+    currentJobId = null;
+    if (job.status === "Failed")
+    {
+        alert(`Simulation failed: ${job.error}`);
+    }
+
+    simulationResults = job.result;
+    updateDisplay(await gameFetch('restart', { method: "POST" }).then(r => r.json()));
+    updateDeckInfo();
+}
+
+async function cancelSimulation()
+{
+    if (currentJobId)
+    {
+        await fetch(`${API_PREFIX}/simulations/${currentJobId}`, { method: "DELETE" });
+    }
+}
+
 async function restart()
 {
     try 
     {
-        const response = await fetch(`${API_PREFIX}/restart`, { method: "POST" });
+        const response = await gameFetch('restart', { method: "POST" });
         const newState = await response.json();
         updateDisplay(newState);
         simulationResults = null;
@@ -93,7 +216,7 @@ async function step()
 {
     try 
     {
-        const response = await fetch(`${API_PREFIX}/step`, { method: "POST" });
+        const response = await gameFetch('step', { method: "POST" });
         const newState = await response.json();
         
         // Immediately update the display with the new state
@@ -124,24 +247,43 @@ async function stopServer()
     }
 }
 
-async function toggleAutoPlay()
+// Seat control is "Human" or { Ai: "<strategy>" } in the serialized state
+function controlToOption(control) {
+    if (control === "Human") return "Human";
+    if (control && control.Ai) return control.Ai;
+    return "Aggressive";
+}
+
+function isHumanSeat(state, playerIndex) {
+    return !!state && !!state.players && !!state.players[playerIndex] && state.players[playerIndex].control === "Human";
+}
+
+async function setPlayerControl(playerIndex, option)
 {
     try 
     {
-        const response = await fetch(`${API_PREFIX}/toggle-auto-play`, { method: "POST" });
-        const newState = await response.json();
-        updateDisplay(newState);
-        
-        // Update the checkbox to reflect the new state
-        const checkbox = document.getElementById('autoPlayCheckbox');
-        checkbox.checked = newState.auto_play;
+        const control = option === "Human" ? "Human" : { Ai: option };
+        const response = await gameFetch('control', {
+            method: "POST",
+            headers: { "Content-Type": "application/json" },
+            body: JSON.stringify({ player_index: playerIndex, control })
+        });
+        if (!response.ok) {
+            await reportApiError(response);
+            return;
+        }
+        updateDisplay(await response.json());
     } 
     catch (e) 
     {
-        console.error("Error toggling auto-play:", e);
+        console.error("Error setting player control:", e);
     }
 }
 
+function isMainPhase(step) {
+    return step === "Main" || step === "SecondMain";
+}
+
 function formatPhase(phase) {
     // Convert GameStep enum to readable text
     const phaseNames = 
@@ -150,28 +292,197 @@ function formatPhase(phase) {
         "Untap": "Untap",
         "Upkeep": "Upkeep",
         "Draw": "Draw",
-        "Main": "Main",
+        "Main": "Precombat Main",
+        "BeginCombat": "Beginning of Combat",
         "DeclareAttackers": "Declare Attackers",
         "DeclareBlockers": "Declare Blockers",
-        "AssignDamage": "Assign Damage",
+        "AssignDamage": "Combat Damage",
+        "EndCombat": "End of Combat",
+        "SecondMain": "Postcombat Main",
+        "EndStep": "End Step",
+        "Cleanup": "Cleanup",
         "EndTurn": "End Turn",
         "GameOver": "Game Over"
     };
     return phaseNames[phase] || phase;
 }
 
+// Show a rules error returned by the server as { error, detail }
+async function reportApiError(response) {
+    let message = `Request failed (${response.status})`;
+    try {
+        const body = await response.json();
+        if (body && body.error) {
+            message = body.error;
+        }
+    } catch (e) {
+        // Non-JSON error body; keep the status message
+    }
+    console.warn("Rejected action:", message);
+    alert(message);
+}
+
+async function playHandCard(action, handIndex) {
+    try {
+        const response = await gameFetch(action, {
+            method: "POST",
+            headers: { "Content-Type": "application/json" },
+            body: JSON.stringify({ hand_index: handIndex })
+        });
+        if (!response.ok) {
+            await reportApiError(response);
+            return;
+        }
+        updateDisplay(await response.json());
+    } catch (e) {
+        console.error(`Error during ${action}:`, e);
+    }
+}
+
+async function passDecision() {
+    try {
+        const response = await gameFetch('pass', { method: "POST" });
+        if (!response.ok) {
+            await reportApiError(response);
+            return;
+        }
+        updateDisplay(await response.json());
+    } catch (e) {
+        console.error("Error passing:", e);
+    }
+}
+
+async function discardCard(handIndex) {
+    try {
+        const response = await gameFetch('discard', {
+            method: "POST",
+            headers: { "Content-Type": "application/json" },
+            body: JSON.stringify({ hand_indices: [handIndex] })
+        });
+        if (!response.ok) {
+            await reportApiError(response);
+            return;
+        }
+        updateDisplay(await response.json());
+    } catch (e) {
+        console.error("Error discarding:", e);
+    }
+}
+
+// Answer a pending "choose targets" with a single player or card
+async function chooseTarget(target) {
+    try {
+        const response = await gameFetch('targets', {
+            method: "POST",
+            headers: { "Content-Type": "application/json" },
+            body: JSON.stringify({ targets: [target] })
+        });
+        if (!response.ok) {
+            await reportApiError(response);
+            return;
+        }
+        updateDisplay(await response.json());
+    } catch (e) {
+        console.error("Error choosing targets:", e);
+    }
+}
+
+// Answer a pending scry, surveil or search with indices into the cards being looked at
+async function chooseLibrary(chosen) {
+    try {
+        const response = await gameFetch('library', {
+            method: "POST",
+            headers: { "Content-Type": "application/json" },
+            body: JSON.stringify({ chosen })
+        });
+        if (!response.ok) {
+            await reportApiError(response);
+            return;
+        }
+        libraryPicks = [];
+        updateDisplay(await response.json());
+    } catch (e) {
+        console.error("Error making library choice:", e);
+    }
+}
+
+// A scry or surveil: click cards in the order they go back on top, then confirm.
+// A search: click the card to take, or take nothing.
+function renderLibraryChoice(state) {
+    const choice = state.library_choice;
+    const isSearch = !!choice.Search;
+    const div = document.createElement("div");
+    div.className = "library-choice";
+    div.style.marginTop = "10px";
+    div.style.textAlign = "center";
+
+    const prompt = document.createElement("p");
+    prompt.textContent = isSearch
+        ? "Search: choose a card"
+        : `${choice.Arrange.surveil ? 'Surveil' : 'Scry'}: click the cards to keep on top, top card first`;
+    div.appendChild(prompt);
+
+    state.looking_at.forEach((card, i) => {
+        const img = document.createElement("img");
+        img.src = `/cards/${encodeURIComponent(card.name)}.jpg`;
+        img.alt = card.name;
+        img.className = "card-image";
+        img.style.cursor = 'pointer';
+        const pick = libraryPicks.indexOf(i);
+        if (pick >= 0) {
+            img.style.outline = "3px solid #FF9800";
+            img.title = `${card.name} - #${pick + 1} on top`;
+        }
+        img.addEventListener('click', () => {
+            if (isSearch) {
+                chooseLibrary([i]);
+                return;
+            }
+            libraryPicks = pick >= 0 ? libraryPicks.filter(p => p !== i) : [...libraryPicks, i];
+            updateDisplay(state);
+        });
+        div.appendChild(img);
+    });
+
+    const confirmBtn = document.createElement("button");
+    confirmBtn.textContent = isSearch ? "Take Nothing" : `Done (${libraryPicks.length} on top)`;
+    confirmBtn.style.padding = "10px 20px";
+    confirmBtn.style.backgroundColor = "#FF9800";
+    confirmBtn.style.color = "white";
+    confirmBtn.style.border = "none";
+    confirmBtn.style.borderRadius = "4px";
+    confirmBtn.style.cursor = "pointer";
+    confirmBtn.style.fontSize = "14px";
+    confirmBtn.addEventListener('click', () => chooseLibrary(isSearch ? [] : libraryPicks));
+    div.appendChild(confirmBtn);
+    return div;
+}
+
+function isChoosingTargets(state) {
+    return !!state && state.waiting_for_targets && isHumanSeat(state, state.deciding_player_index);
+}
+
 async function declareAttackers() {
     try {
         const payload = {
-            attacking_indices: Array.from(selectedAttackers)
+            attacking_indices: Array.from(selectedAttackers),
+            targets: {}
         };
-        const response = await fetch(`${API_PREFIX}/declare-attackers`, {
+        if (attackTarget !== null) {
+            payload.attacking_indices.forEach(i => { payload.targets[i] = attackTarget; });
+        }
+        const response = await gameFetch('declare-attackers', {
             method: "POST",
             headers: { "Content-Type": "application/json" },
             body: JSON.stringify(payload)
         });
+        if (!response.ok) {
+            await reportApiError(response);
+            return;
+        }
         const newState = await response.json();
         selectedAttackers.clear();
+        attackTarget = null;
         updateDisplay(newState);
     } catch (e) {
         console.error("Error declaring attackers:", e);
@@ -185,11 +496,15 @@ async function declareBlockers() {
             blocking_map[blocker] = attacker;
         }
         const payload = { blocking_map };
-        const response = await fetch(`${API_PREFIX}/declare-blockers`, {
+        const response = await gameFetch('declare-blockers', {
             method: "POST",
             headers: { "Content-Type": "application/json" },
             body: JSON.stringify(payload)
         });
+        if (!response.ok) {
+            await reportApiError(response);
+            return;
+        }
         const newState = await response.json();
         selectedBlockers.clear();
         updateDisplay(newState);
@@ -408,6 +723,12 @@ function drawAutoPlayBlockingArrows() {
     document.body.appendChild(svg);
 }
 
+// { "Forest": 29, "Grizzly Bears": 31 } -> "29 Forest, 31 Grizzly Bears"
+function formatDeckList(deck)
+{
+    return Object.entries(deck).map(([name, count]) => `${count} ${name}`).join(", ");
+}
+
 function updateDeckInfo()
 {
     const deckComp = document.getElementById("deck-composition");
@@ -415,8 +736,22 @@ function updateDeckInfo()
     
     if (simulationResults) 
     {
-        deckComp.textContent = `Deck: 29 Forests, 31 Grizzly Bears`;
+        // Sweeps report every configuration; show the best one
+        const best = simulationResults.best;
+        deckComp.textContent = best ? `Deck: ${formatDeckList(best.deck)}` : `Deck: 29 Forests, 31 Grizzly Bears`;
         results.textContent = `Results: Avg ${simulationResults.avg_turns.toFixed(2)} turns over ${simulationResults.total_games} games`;
+        if (best && simulationResults.results.length > 1)
+        {
+            results.textContent += ` (best of ${simulationResults.results.length} configurations: ${best.avg_turns.toFixed(2)} turns, ${(best.win_rate * 100).toFixed(1)}% wins, seed ${simulationResults.seed})`;
+        }
+        if (best && best.avg_discards > 0)
+        {
+            results.textContent += `, ${best.avg_discards.toFixed(2)} cards discarded to hand size per game`;
+        }
+        if (best && best.avg_matching !== null && best.avg_matching !== undefined)
+        {
+            results.textContent += `, ${best.avg_matching.toFixed(2)} matching cards at game end`;
+        }
     } 
     else 
     {
@@ -444,6 +779,29 @@ function cardIsTapped(card)
     return false;
 }
 
+// Counters on a card as { kind: count }, e.g. { "+1/+1": 2 }
+function cardCounters(card)
+{
+    const f = card && card.fragments && card.fragments.Counters;
+    if (!f)
+    {
+        return {};
+    }
+    return (f.Counters ? f.Counters.counters : f.counters) || {};
+}
+
+// Show a card's counters as a tooltip and a highlight around the card
+function decorateCounters(img, card)
+{
+    const entries = Object.entries(cardCounters(card));
+    if (entries.length === 0)
+    {
+        return;
+    }
+    img.classList.add('has-counters');
+    img.title = `${card.name} - ${entries.map(([kind, count]) => `${kind} x${count}`).join(', ')}`;
+}
+
 // Render a single player's zones
 function renderPlayerZones(player, playerIndex, isCurrentPlayer)
 {
@@ -452,6 +810,46 @@ function renderPlayerZones(player, playerIndex, isCurrentPlayer)
     
     const title = document.createElement("h2");
     title.textContent = `Player ${playerIndex}${isCurrentPlayer ? ' (Current)' : ''} - ${player.life} Life`;
+    if (player.mana_pool > 0) {
+        title.textContent += ` - ${player.mana_pool} Mana Floating`;
+    }
+    if (player.eliminated) {
+        title.textContent += ' - Eliminated';
+    }
+
+    // Team games (Two-Headed Giant): teammates share the life shown above
+    const state = currentGameState;
+    const teams = (state && state.teams) || [];
+    const team = teams.findIndex(members => members.includes(playerIndex));
+    if (team >= 0) {
+        title.textContent += ` - Team ${team + 1}`;
+    }
+    const isTeammate = team >= 0 && teams[team].includes(state.current_player_index);
+
+    // A triggered ability is waiting for its controller to pick a target: players can be clicked
+    if (isChoosingTargets(state)) {
+        const pending = state.pending_triggers[0];
+        if (pending && pending.controller === playerIndex) {
+            title.textContent += ` - Choose a target for ${pending.source_name}`;
+        }
+        if (!player.eliminated) {
+            title.style.cursor = 'crosshair';
+            title.addEventListener('click', () => chooseTarget({ Player: playerIndex }));
+        }
+    }
+
+    // Free-for-all: click an opponent's name to send the selected attackers at them
+    if (state && state.players.length > 2 && state.step === "DeclareAttackers" && !isCurrentPlayer
+        && !isTeammate && !player.eliminated && isHumanSeat(state, state.current_player_index)) {
+        if (attackTarget === playerIndex) {
+            title.textContent += ' (Attack Target)';
+        }
+        title.style.cursor = 'pointer';
+        title.addEventListener('click', () => {
+            attackTarget = playerIndex;
+            updateDisplay(state);
+        });
+    }
     if (!isCurrentPlayer) {
         title.style.transform = 'rotate(180deg)';
         title.style.transformOrigin = 'center';
@@ -469,7 +867,8 @@ function renderPlayerZones(player, playerIndex, isCurrentPlayer)
     battlefieldDiv.appendChild(battlefieldTitle);
     
     const bfCards = player.zones.Battlefield || [];
-    const grizzlies = bfCards.map((c, idx) => ({ card: c, index: idx })).filter(c => c.card.name === "Grizzly Bears");
+    // Everything that isn't a land sits in the creature row, so tokens and mana dorks show up too
+    const grizzlies = bfCards.map((c, idx) => ({ card: c, index: idx })).filter(c => !c.card.card_types.includes("Land"));
     const forests = bfCards.filter(c => c.name === "Forest");
 
     const grizzliesContainer = document.createElement("div");
@@ -482,6 +881,7 @@ function renderPlayerZones(player, playerIndex, isCurrentPlayer)
         img.src = `/cards/${encodeURIComponent(card.name)}.jpg`;
         img.className = "card";
         img.alt = card.name;
+        decorateCounters(img, card);
         img.dataset.creatureIndex = creatureIndex;
         img.dataset.playerIndex = playerIndex;
         
@@ -508,8 +908,11 @@ function renderPlayerZones(player, playerIndex, isCurrentPlayer)
             img.style.transform = img.dataset.baseTransform;
         });
 
-        // Add click handling for attack declaration and blocking
-        if (isCurrentPlayer && currentGameState && currentGameState.step === "DeclareAttackers") {
+        // Add click handling for targeting, attack declaration and blocking
+        if (isChoosingTargets(currentGameState)) {
+            img.style.cursor = 'crosshair';
+            img.addEventListener('click', () => chooseTarget({ Card: card.id }));
+        } else if (isCurrentPlayer && currentGameState && currentGameState.step === "DeclareAttackers") {
             img.classList.add('attacker-card');
             img.style.cursor = 'pointer';
             img.style.border = selectedAttackers.has(creatureIndex) ? '3px solid red' : '3px solid transparent';
@@ -562,6 +965,7 @@ function renderPlayerZones(player, playerIndex, isCurrentPlayer)
             img.src = `/cards/${encodeURIComponent(card.name)}.jpg`;
             img.className = "card";
             img.alt = card.name;
+            decorateCounters(img, card);
 
             const left = startX + i * overlap;
             img.style.left = `${left}px`;
@@ -595,6 +999,7 @@ function renderPlayerZones(player, playerIndex, isCurrentPlayer)
             img.src = `/cards/${encodeURIComponent(card.name)}.jpg`;
             img.className = "card";
             img.alt = card.name;
+            decorateCounters(img, card);
 
             // small horizontal offset between stacked tapped cards
             const left = stackXBase + i * 6;
@@ -636,7 +1041,11 @@ function renderPlayerZones(player, playerIndex, isCurrentPlayer)
     // Library
     const libraryDiv = document.createElement("div");
     libraryDiv.className = "library-section";
-    const libraryCards = player.zones.Library || [];
+    // The server never sends library contents, only how many cards are left
+    const libraryCount = currentGameState && currentGameState.library_counts
+        ? currentGameState.library_counts[playerIndex]
+        : (player.zones.Library || []).length;
+    const libraryCards = Array.from({ length: libraryCount });
     
     const LIB_CARD_W = 60;
     const LIB_CARD_H = 80;
@@ -716,13 +1125,21 @@ function renderPlayerZones(player, playerIndex, isCurrentPlayer)
             img.style.zIndex = `${i}`;
             img.style.transform = '';
         });
+        // Abilities can return cards from graveyards
+        if (isChoosingTargets(currentGameState)) {
+            img.style.cursor = 'crosshair';
+            img.addEventListener('click', () => chooseTarget({ Card: card.id }));
+        }
         
         graveyardDiv.appendChild(img);
     });
     
     const gyLabel = document.createElement("p");
     gyLabel.className = "zone-label";
-    gyLabel.textContent = `Graveyard (${graveyardCards.length})`;
+    const exiledCount = (player.zones.Exile || []).length;
+    gyLabel.textContent = exiledCount > 0
+        ? `Graveyard (${graveyardCards.length}) · Exile (${exiledCount})`
+        : `Graveyard (${graveyardCards.length})`;
     if (!isCurrentPlayer) gyLabel.style.transform = 'rotate(180deg)';
     graveyardDiv.appendChild(gyLabel);
     handLibContent.appendChild(graveyardDiv);
@@ -730,7 +1147,13 @@ function renderPlayerZones(player, playerIndex, isCurrentPlayer)
     // Hand
     const handDiv = document.createElement("div");
     handDiv.className = "hand-section";
-    const handCards = player.zones.Hand || [];
+    // Hands we can't see arrive empty; draw card backs for the real count instead
+    const visibleHand = player.zones.Hand || [];
+    const handCount = currentGameState && currentGameState.hand_counts
+        ? currentGameState.hand_counts[playerIndex]
+        : visibleHand.length;
+    const handHidden = visibleHand.length < handCount;
+    const handCards = handHidden ? Array.from({ length: handCount }, () => null) : visibleHand;
 
     const CARD_W = 60;
     const CARD_H = 80;
@@ -751,10 +1174,10 @@ function renderPlayerZones(player, playerIndex, isCurrentPlayer)
     handCards.forEach((card, i) => 
     {
         const img = document.createElement("img");
-            // Use back of card for inactive player's hand
-            img.src = isCurrentPlayer ? `/cards/${encodeURIComponent(card.name)}.jpg` : `/cards/back.jpg`;
+            // Use back of card for hands this seat can't see
+            img.src = card ? `/cards/${encodeURIComponent(card.name)}.jpg` : `/cards/back.jpg`;
         img.className = "card";
-        img.alt = card.name;
+        img.alt = card ? card.name : "card back";
         img.style.width = `${CARD_W}px`;
         img.style.height = `${CARD_H}px`;
 
@@ -780,6 +1203,23 @@ function renderPlayerZones(player, playerIndex, isCurrentPlayer)
         });
         img.style.zIndex = `${i * 10}`;
 
+        // Manual main phase: click a land to play it or a creature to cast it
+        if (card && isCurrentPlayer && currentGameState && isMainPhase(currentGameState.step) && isHumanSeat(currentGameState, playerIndex)) {
+            img.style.cursor = 'pointer';
+            img.addEventListener('click', () => {
+                if (card.card_types.includes("Land")) {
+                    playHandCard('play-land', i);
+                } else {
+                    playHandCard('cast', i);
+                }
+            });
+        }
+        // Cleanup with too many cards: click a card to discard it
+        else if (card && isCurrentPlayer && currentGameState && currentGameState.waiting_for_discard && isHumanSeat(currentGameState, playerIndex)) {
+            img.style.cursor = 'pointer';
+            img.addEventListener('click', () => discardCard(i));
+        }
+
         handDiv.appendChild(img);
     });
     
@@ -793,6 +1233,13 @@ function renderPlayerZones(player, playerIndex, isCurrentPlayer)
     handLibraryDiv.appendChild(handLibContent);
     container.appendChild(handLibraryDiv);
 
+    // This seat is making a library choice
+    const libraryChoice = state && state.library_choice;
+    if (libraryChoice && state.deciding_player_index === playerIndex && isHumanSeat(state, playerIndex)
+        && (state.looking_at || []).length > 0) {
+        container.appendChild(renderLibraryChoice(state));
+    }
+
     // Add action buttons for current player during combat phases
     if (isCurrentPlayer && currentGameState) {
         const actionDiv = document.createElement("div");
@@ -800,7 +1247,19 @@ function renderPlayerZones(player, playerIndex, isCurrentPlayer)
         actionDiv.style.marginTop = "10px";
         actionDiv.style.textAlign = "center";
         
-        if (currentGameState.step === "DeclareAttackers") {
+        if (isMainPhase(currentGameState.step) && isHumanSeat(currentGameState, playerIndex)) {
+            const passBtn = document.createElement("button");
+            passBtn.textContent = currentGameState.step === "Main" ? "Go to Combat" : "End Turn";
+            passBtn.style.padding = "10px 20px";
+            passBtn.style.backgroundColor = "#9C27B0";
+            passBtn.style.color = "white";
+            passBtn.style.border = "none";
+            passBtn.style.borderRadius = "4px";
+            passBtn.style.cursor = "pointer";
+            passBtn.style.fontSize = "14px";
+            passBtn.addEventListener('click', passDecision);
+            actionDiv.appendChild(passBtn);
+        } else if (currentGameState.step === "DeclareAttackers") {
             const confirmBtn = document.createElement("button");
             confirmBtn.textContent = `Declare Attackers (${selectedAttackers.size} selected)`;
             confirmBtn.style.padding = "10px 20px";
@@ -841,11 +1300,13 @@ function updateDisplay(state)
         return;
     }
 
-    // Update auto-play checkbox state
-    const checkbox = document.getElementById('autoPlayCheckbox');
-    if (checkbox) {
-        checkbox.checked = state.auto_play;
-    }
+    // Update seat control selectors
+    (state.players || []).forEach((player, index) => {
+        const select = document.getElementById(`control${index}`);
+        if (select) {
+            select.value = controlToOption(player.control);
+        }
+    });
 
     // Update phase, current player, and turns
     const phaseElement = document.getElementById("phase");
@@ -865,6 +1326,9 @@ function updateDisplay(state)
         playersHealthElement.textContent = healthText;
     }
 
+    // Store current game state for UI functions; the player sections read it while rendering
+    currentGameState = state;
+
     // Render all players' zones in circular wedge arrangement
     const playersContainer = document.getElementById("players-container");
     
@@ -914,15 +1378,15 @@ function updateDisplay(state)
         });
     }
 
-    // Store current game state for UI functions
-    currentGameState = state;
-
-    // If the game reached GameOver, auto-restart to next game after short delay
-    if (state.step === "GameOver") 
+    // If the game reached GameOver, auto-restart to next game after short delay.
+    // Pushed updates can deliver the same GameOver state more than once, so only schedule one restart.
+    if (state.step === "GameOver" && !restartScheduled) 
     {
+        restartScheduled = true;
         setTimeout(() => 
         {
             // Restart engine to next game
+            restartScheduled = false;
             restart();
         }, 3000);
     }
@@ -1075,8 +1539,52 @@ function toggleMusic()
     localStorage.setItem('musicEnabled', musicEnabled);
 }
 
+// Live updates pushed by the server as server-sent events
+let gameEvents = null;
+let restartScheduled = false;
+
+function subscribeToGame()
+{
+    if (gameEvents)
+    {
+        gameEvents.close();
+    }
+    if (!gameId)
+    {
+        return;
+    }
+
+    // EventSource can't send headers, so the seat token rides in the query string
+    const query = seatToken ? `?token=${encodeURIComponent(seatToken)}` : '';
+    gameEvents = new EventSource(gameUrl('events') + query);
+    gameEvents.onmessage = (msg) =>
+    {
+        const update = JSON.parse(msg.data);
+        updateDisplay(update.state);
+    };
+}
+
+function subscribeToSimulations()
+{
+    const simEvents = new EventSource(`${API_PREFIX}/simulations/events`);
+    simEvents.onmessage = (msg) =>
+    {
+        const progress = JSON.parse(msg.data);
+        const results = document.getElementById("results");
+        if (progress.completed < progress.total)
+        {
+            results.textContent = `Running ${progress.label}: ${progress.completed}/${progress.total} games, avg ${progress.avg_turns.toFixed(2)} turns so far`;
+        }
+    };
+}
+
 // Initial render and setup
-render();
+render().then(() =>
+{
+    showInviteLink();
+    subscribeToGame();
+});
+subscribeToSimulations();
 updateDeckInfo();
 
 // Load and play music